@@ -1 +1,148 @@
-pub use crate::types::*; 
\ No newline at end of file
+use crate::*;
+
+impl Contract {
+    /// Fund an escrow with tokens its depositor transferred to the contract.
+    /// Returns the amount used, the rest is refunded by the token contract.
+    pub(crate) fn internal_fund_escrow(
+        &mut self,
+        escrow_id: &String,
+        token_id: &AccountId,
+        sender_id: &AccountId,
+        amount: u128,
+        secret_hash: String,
+    ) -> u128 {
+        require!(!self.paused, "Contract is paused");
+
+        let mut escrow = self
+            .escrows
+            .get(escrow_id)
+            .expect("Escrow not found")
+            .clone();
+
+        require!(
+            escrow.status == EscrowStatus::Created,
+            "Escrow not in created state"
+        );
+        require!(
+            escrow.depositor() == sender_id,
+            "Only the escrow's depositor can fund it"
+        );
+        require!(
+            escrow.locked_asset() == token_id,
+            "Token is not the escrow's locked asset"
+        );
+        require!(
+            amount >= escrow.locked_amount(),
+            "Transferred amount is less than the escrow amount"
+        );
+
+        // Update escrow
+        escrow.status = EscrowStatus::Funded;
+        escrow.secret_hash = normalize_bytes32(&secret_hash);
+        escrow.funded_at = Some(env::block_timestamp());
+
+        // Store updated escrow
+        self.escrows.insert(escrow_id.clone(), escrow.clone());
+
+        // Emit event
+        EscrowFunded {
+            escrow_id: escrow_id.clone(),
+            depositor: escrow.depositor().clone(),
+            secret_hash: escrow.secret_hash.clone(),
+        }
+        .emit();
+
+        escrow.locked_amount()
+    }
+
+    /// Close an escrow that was never funded and give its safety deposit back
+    /// to its creator, once the cancellation time counted from its creation
+    /// has passed
+    pub(crate) fn internal_cancel_unfunded_escrow(&mut self, mut escrow: Escrow) -> Promise {
+        require!(
+            env::block_timestamp()
+                >= escrow
+                    .created_at
+                    .saturating_add(escrow.timelocks.cancellation),
+            "Not in cancellation period"
+        );
+
+        escrow.status = EscrowStatus::Cancelled;
+        self.escrows.insert(escrow.id.clone(), escrow.clone());
+
+        EscrowCancelled {
+            escrow_id: escrow.id.clone(),
+            receiver: escrow.depositor().clone(),
+            amount: 0,
+            safety_deposit: escrow.safety_deposit,
+            safety_deposit_receiver: escrow.creator.clone(),
+        }
+        .emit();
+
+        self.internal_pay_safety_deposit(escrow.creator, escrow.safety_deposit)
+    }
+
+    /// Pay `amount` of an escrow's safety deposit to the account that
+    /// withdrew or cancelled it. `on_safety_deposit_paid` keeps a failed
+    /// payout for the receiver to claim.
+    pub(crate) fn internal_pay_safety_deposit(
+        &self,
+        receiver_id: AccountId,
+        amount: u128,
+    ) -> Promise {
+        Promise::new(receiver_id.clone())
+            .transfer(NearToken::from_yoctonear(amount))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_SAFETY_DEPOSIT_CALLBACK)
+                    .on_safety_deposit_paid(receiver_id, U128(amount)),
+            )
+    }
+}
+
+impl Escrow {
+    /// Check `secret` unlocks the next part of the escrow. Parts of a Merkle
+    /// tree escrow are withdrawn in order, with the secret at their `index`
+    /// and its `proof`.
+    pub fn assert_valid_secret(&self, secret: &str, index: Option<u32>, proof: &[String]) {
+        let valid = match self.secret_parts {
+            None => self.hashlock.verify(secret, &self.secret_hash),
+            Some(_) => {
+                let index = index.expect("Index is required for Merkle tree secrets");
+                require!(
+                    index == self.withdrawn_parts,
+                    "Secrets must be revealed in order"
+                );
+                self.hashlock
+                    .verify_merkle(secret, index, proof, &self.secret_hash)
+            }
+        };
+        require!(valid, "Invalid secret");
+    }
+
+    /// The taker withdraws during the withdrawal stage, anyone during the
+    /// public withdrawal
+    pub fn assert_can_withdraw(&self, caller: &AccountId) {
+        match self.current_stage() {
+            TimelockStage::Withdrawal => require!(
+                caller == &self.taker,
+                "Only taker can withdraw before the public withdrawal"
+            ),
+            TimelockStage::PublicWithdrawal => {}
+            _ => env::panic_str("Not in withdrawal period"),
+        }
+    }
+
+    /// The taker cancels during the cancellation stage, anyone during the
+    /// public cancellation of a source escrow
+    pub fn assert_can_cancel(&self, caller: &AccountId) {
+        match self.current_stage() {
+            TimelockStage::Cancellation => require!(
+                caller == &self.taker,
+                "Only taker can cancel before the public cancellation"
+            ),
+            TimelockStage::PublicCancellation => {}
+            _ => env::panic_str("Not in cancellation period"),
+        }
+    }
+}
@@ -3,23 +3,28 @@ use near_sdk::{
     AccountId,
 };
 
+use crate::types::EscrowKind;
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct EscrowCreated {
     pub escrow_id: String,
+    pub kind: EscrowKind,
     pub maker: AccountId,
     pub taker: AccountId,
     pub maker_asset: AccountId,
     pub taker_asset: AccountId,
     pub making_amount: u128,
     pub taking_amount: u128,
+    pub safety_deposit: u128,
+    pub creator: AccountId,
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct EscrowFunded {
     pub escrow_id: String,
-    pub taker: AccountId,
+    pub depositor: AccountId,
     pub secret_hash: String,
 }
 
@@ -29,13 +34,21 @@ pub struct EscrowWithdrawn {
     pub escrow_id: String,
     pub secret: String,
     pub receiver: AccountId,
+    /// Part of an escrow with Merkle tree secrets the secret unlocked
+    pub index: Option<u32>,
+    pub amount: u128,
+    pub safety_deposit: u128,
+    pub safety_deposit_receiver: AccountId,
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct EscrowCancelled {
     pub escrow_id: String,
-    pub taker: AccountId,
+    pub receiver: AccountId,
+    pub amount: u128,
+    pub safety_deposit: u128,
+    pub safety_deposit_receiver: AccountId,
 }
 
 #[derive(Serialize, Deserialize)]
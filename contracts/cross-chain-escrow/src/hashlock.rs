@@ -0,0 +1,51 @@
+use crate::*;
+
+impl HashlockAlgorithm {
+    pub fn hash(&self, value: &[u8]) -> Vec<u8> {
+        match self {
+            HashlockAlgorithm::Sha256 => env::sha256(value),
+            HashlockAlgorithm::Keccak256 => env::keccak256(value),
+        }
+    }
+
+    /// Whether `secret` hashes to `secret_hash`, both 32-byte hex values
+    pub fn verify(&self, secret: &str, secret_hash: &str) -> bool {
+        self.hash(&parse_bytes32(secret)) == parse_bytes32(secret_hash)
+    }
+
+    /// Whether `secret` is the secret of part `index` in the Merkle tree of
+    /// secret hashes with `root`. As in 1inch Fusion+, leaves hash the
+    /// `uint64` index followed by the secret hash and nodes hash their
+    /// children in sorted order.
+    pub fn verify_merkle(&self, secret: &str, index: u32, proof: &[String], root: &str) -> bool {
+        let secret_hash = self.hash(&parse_bytes32(secret));
+        let mut node = self.hash(&[&(index as u64).to_be_bytes()[..], &secret_hash].concat());
+        for sibling in proof {
+            let sibling = parse_bytes32(sibling);
+            node = if node.as_slice() <= &sibling[..] {
+                self.hash(&[&node[..], &sibling].concat())
+            } else {
+                self.hash(&[&sibling[..], &node].concat())
+            };
+        }
+        node == parse_bytes32(root)
+    }
+}
+
+/// Parse a 32-byte value in hex, with or without the `0x` prefix
+pub fn parse_bytes32(value: &str) -> [u8; 32] {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    let bytes = hex::decode(digits).unwrap_or_else(|_| env::panic_str("Invalid hex value"));
+    bytes
+        .try_into()
+        .unwrap_or_else(|_| env::panic_str("Expected a 32-byte hex value"))
+}
+
+/// Normalize a 32-byte hex value to `0x` prefixed lowercase hex, the form
+/// EVM tooling uses for `bytes32`
+pub fn normalize_bytes32(value: &str) -> String {
+    format!("0x{}", hex::encode(parse_bytes32(value)))
+}
@@ -1,76 +1,76 @@
 use near_sdk::{
-    borsh::{BorshDeserialize, BorshSerialize},
-    env, near, AccountId, Gas, NearToken, PanicOnDefault, Promise, Timestamp,
-    serde::{Deserialize, Serialize},
-    ext_contract, require, assert_one_yocto,
+    env, json_types::U128, near, AccountId, Gas, NearToken, PanicOnDefault, Promise,
+    ext_contract, require, assert_one_yocto, PromiseError,
 };
 
+mod escrow;
 mod events;
-mod ext;
+mod hashlock;
+mod token_receiver;
 mod types;
 mod timelocks;
 
 use events::*;
+use hashlock::*;
 use types::*;
 use timelocks::*;
 
 // Gas constants
 const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
-const GAS_FOR_INTENTS_CALL: Gas = Gas::from_tgas(30);
 const GAS_FOR_ESCROW_CALLBACK: Gas = Gas::from_tgas(20);
+const GAS_FOR_SAFETY_DEPOSIT_CALLBACK: Gas = Gas::from_tgas(5);
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
 pub struct Contract {
     pub owner_id: AccountId,
-    pub intents_contract_id: AccountId,
     pub limit_order_protocol_id: AccountId,
     pub escrows: std::collections::HashMap<String, Escrow>,
     pub escrow_by_maker: std::collections::HashMap<AccountId, Vec<String>>,
     pub escrow_by_taker: std::collections::HashMap<AccountId, Vec<String>>,
+    /// Safety deposits whose payout failed, claimable by their receiver
+    pub unpaid_safety_deposits: std::collections::HashMap<AccountId, u128>,
     pub paused: bool,
 }
 
 #[ext_contract(ext_ft)]
 trait FungibleToken {
-    fn ft_transfer(&mut self, receiver_id: AccountId, amount: u128, memo: Option<String>);
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
     fn ft_transfer_call(
         &mut self,
         receiver_id: AccountId,
-        amount: u128,
+        amount: U128,
         msg: String,
     ) -> Promise;
 }
 
-#[ext_contract(ext_intents)]
-trait IntentsContract {
-    fn deposit(token_id: AccountId, amount: u128, receiver_id: AccountId);
-    fn withdraw(token_id: AccountId, amount: u128, receiver_id: AccountId);
-}
-
 #[near]
 impl Contract {
     #[init]
     pub fn new(
         owner_id: AccountId,
-        intents_contract_id: AccountId,
         limit_order_protocol_id: AccountId,
     ) -> Self {
         Self {
             owner_id,
-            intents_contract_id,
             limit_order_protocol_id,
             escrows: std::collections::HashMap::new(),
             escrow_by_maker: std::collections::HashMap::new(),
             escrow_by_taker: std::collections::HashMap::new(),
+            unpaid_safety_deposits: std::collections::HashMap::new(),
             paused: false,
         }
     }
 
-    /// Create a new escrow
+    /// Create a new escrow. Escrows are created by their taker, the resolver,
+    /// and the attached NEAR is its safety deposit, paid to whoever withdraws
+    /// or cancels the escrow. With `secret_parts` the
+    /// escrow is funded with the Merkle root of that many secret hashes and
+    /// withdrawn in equal parts, one per secret.
     #[payable]
     pub fn create_escrow(
         &mut self,
+        kind: EscrowKind,
         maker_asset: AccountId,
         taker_asset: AccountId,
         making_amount: u128,
@@ -80,15 +80,21 @@ impl Contract {
         source_chain: String,
         destination_chain: String,
         timelocks: Timelocks,
-        safety_deposit: u128,
+        hashlock: HashlockAlgorithm,
+        secret_parts: Option<u32>,
     ) -> String {
         require!(!self.paused, "Contract is paused");
         require!(making_amount > 0, "Making amount must be greater than 0");
         require!(taking_amount > 0, "Taking amount must be greater than 0");
         require!(maker_asset != taker_asset, "Maker and taker assets must be different");
+        timelocks.assert_valid(&kind);
+        require!(secret_parts != Some(0), "Secret parts must be greater than 0");
+        let creator = env::predecessor_account_id();
+        require!(creator == taker, "Only the taker can create an escrow");
 
         // Generate escrow ID
         let escrow_id = self.generate_escrow_id(
+            &kind,
             &maker_asset,
             &taker_asset,
             making_amount,
@@ -97,6 +103,10 @@ impl Contract {
             &taker,
             &source_chain,
             &destination_chain,
+            &timelocks,
+            &hashlock,
+            secret_parts,
+            &creator,
         );
 
         require!(!self.escrows.contains_key(&escrow_id), "Escrow already exists");
@@ -104,17 +114,24 @@ impl Contract {
         // Create escrow
         let escrow = Escrow {
             id: escrow_id.clone(),
+            kind,
             maker,
             taker,
             maker_asset,
             taker_asset,
             making_amount,
             taking_amount,
+            hashlock,
             secret_hash: String::new(), // Will be set when funded
+            secret_parts,
+            withdrawn_parts: 0,
             timelocks,
             status: EscrowStatus::Created,
             created_at: env::block_timestamp(),
             funded_at: None,
+            safety_deposit: env::attached_deposit().as_yoctonear(),
+            transfer_in_flight: false,
+            creator,
         };
 
         // Store escrow
@@ -135,86 +152,32 @@ impl Contract {
         // Emit event
         EscrowCreated {
             escrow_id: escrow_id.clone(),
+            kind: escrow.kind,
             maker: escrow.maker,
             taker: escrow.taker,
             maker_asset: escrow.maker_asset,
             taker_asset: escrow.taker_asset,
             making_amount: escrow.making_amount,
             taking_amount: escrow.taking_amount,
+            safety_deposit: escrow.safety_deposit,
+            creator: escrow.creator,
         }
         .emit();
 
         escrow_id
     }
 
-    /// Deposit funds into escrow
-    #[payable]
-    pub fn deposit_into_escrow(
-        &mut self,
-        escrow_id: String,
-        secret_hash: String,
-    ) -> Promise {
-        assert_one_yocto();
-        require!(!self.paused, "Contract is paused");
-
-        let mut escrow = self
-            .escrows
-            .get(&escrow_id)
-            .expect("Escrow not found")
-            .clone();
-
-        require!(escrow.status == EscrowStatus::Created, "Escrow not in created state");
-        require!(
-            env::predecessor_account_id() == escrow.taker,
-            "Only taker can deposit"
-        );
-
-        // Update escrow
-        escrow.status = EscrowStatus::Funded;
-        escrow.secret_hash = secret_hash.clone();
-        escrow.funded_at = Some(env::block_timestamp());
-
-        // Store updated escrow
-        self.escrows.insert(escrow_id.clone(), escrow.clone());
-
-        // Call intents contract to deposit funds
-        let deposit_promise = ext_intents::ext(self.intents_contract_id.clone())
-            .with_attached_deposit(NearToken::from_yoctonear(1))
-            .with_static_gas(GAS_FOR_ESCROW_CALLBACK)
-            .deposit(
-                escrow.taker_asset.clone(),
-                escrow.taking_amount,
-                env::current_account_id(),
-            );
-
-        // Emit event
-        EscrowFunded {
-            escrow_id: escrow_id.clone(),
-            taker: escrow.taker,
-            secret_hash,
-        }
-        .emit();
-
-        deposit_promise
-    }
-
-    /// Callback after deposit
-    #[private]
-    pub fn on_escrow_deposited(
-        &mut self,
-        escrow_id: String,
-        secret_hash: String,
-    ) {
-        // Escrow is now funded and ready for withdrawal
-    }
-
-    /// Withdraw from escrow
+    /// Withdraw the escrowed funds to the beneficiary by revealing the secret.
+    /// Escrows with Merkle tree secrets release the part at `index`, proven
+    /// by `proof`, and its share of the safety deposit.
     #[payable]
     pub fn withdraw_from_escrow(
         &mut self,
         escrow_id: String,
         secret: String,
         receiver: Option<AccountId>,
+        index: Option<u32>,
+        proof: Option<Vec<String>>,
     ) -> Promise {
         assert_one_yocto();
         require!(!self.paused, "Contract is paused");
@@ -226,59 +189,99 @@ impl Contract {
             .clone();
 
         require!(escrow.status == EscrowStatus::Funded, "Escrow not funded");
-        require!(
-            self.verify_secret(&secret, &escrow.secret_hash),
-            "Invalid secret"
-        );
+        require!(!escrow.transfer_in_flight, "Escrow transfer in progress");
+        escrow.assert_valid_secret(&secret, index, &proof.unwrap_or_default());
 
-        // Check if withdrawal period has started
-        let current_stage = escrow.timelocks.get_current_stage(escrow.created_at);
-        require!(
-            matches!(current_stage, TimelockStage::Withdrawal | TimelockStage::PublicWithdrawal),
-            "Not in withdrawal period"
-        );
+        let caller = env::predecessor_account_id();
+        escrow.assert_can_withdraw(&caller);
 
-        // Update escrow
-        escrow.status = EscrowStatus::Withdrawn;
+        let part = escrow.withdrawn_parts;
+        let amount = escrow.parts_share(escrow.locked_amount(), part, part + 1);
+        let safety_deposit = escrow.parts_share(escrow.safety_deposit, part, part + 1);
+
+        // Update escrow, `on_escrow_withdrawn` restores it if the transfer fails
+        escrow.withdrawn_parts += 1;
+        if escrow.withdrawn_parts == escrow.parts() {
+            escrow.status = EscrowStatus::Withdrawn;
+        }
+        escrow.transfer_in_flight = true;
 
         // Store updated escrow
         self.escrows.insert(escrow_id.clone(), escrow.clone());
 
-        let receiver_id = receiver.unwrap_or_else(|| env::predecessor_account_id());
+        // Only the taker of a source escrow may redirect the funds it withdraws
+        let receiver_id = match receiver {
+            Some(receiver_id) => {
+                require!(
+                    escrow.kind == EscrowKind::Src && caller == escrow.taker,
+                    "Only the taker of a source escrow can set the receiver"
+                );
+                receiver_id
+            }
+            None => escrow.beneficiary().clone(),
+        };
 
         // Transfer funds to receiver
-        let transfer_promise = ext_ft::ext(escrow.maker_asset.clone())
+        ext_ft::ext(escrow.locked_asset().clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(GAS_FOR_FT_TRANSFER)
             .ft_transfer(
                 receiver_id.clone(),
-                escrow.making_amount,
+                U128(amount),
                 Some(format!("Withdraw from escrow {}", escrow_id)),
-            );
-
-        // Emit event
-        EscrowWithdrawn {
-            escrow_id: escrow_id.clone(),
-            receiver: receiver_id,
-            secret,
-        }
-        .emit();
-
-        transfer_promise
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ESCROW_CALLBACK)
+                    .on_escrow_withdrawn(EscrowWithdrawn {
+                        escrow_id: escrow_id.clone(),
+                        receiver: receiver_id,
+                        secret: normalize_bytes32(&secret),
+                        index: escrow.secret_parts.map(|_| part),
+                        amount,
+                        safety_deposit,
+                        safety_deposit_receiver: caller,
+                    }),
+            )
     }
 
-    /// Callback after withdrawal
+    /// Callback after the withdrawn funds were sent. Pays the safety deposit,
+    /// or gives the part back to the escrow if the transfer failed.
     #[private]
     pub fn on_escrow_withdrawn(
         &mut self,
-        escrow_id: String,
-        secret: String,
-        receiver_id: AccountId,
-    ) {
-        // Escrow has been successfully withdrawn
+        withdrawal: EscrowWithdrawn,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> bool {
+        let mut escrow = self
+            .escrows
+            .get(&withdrawal.escrow_id)
+            .expect("Escrow not found")
+            .clone();
+        escrow.transfer_in_flight = false;
+
+        if call_result.is_err() {
+            escrow.status = EscrowStatus::Funded;
+            escrow.withdrawn_parts -= 1;
+            self.escrows.insert(withdrawal.escrow_id, escrow);
+            return false;
+        }
+        self.escrows.insert(withdrawal.escrow_id.clone(), escrow);
+
+        if withdrawal.safety_deposit > 0 {
+            self.internal_pay_safety_deposit(
+                withdrawal.safety_deposit_receiver.clone(),
+                withdrawal.safety_deposit,
+            );
+        }
+        withdrawal.emit();
+
+        true
     }
 
-    /// Cancel escrow
+    /// Cancel escrow and refund the depositor. Escrows never funded are closed
+    /// once their cancellation time, counted from creation, has passed, and
+    /// their safety deposit goes back to the creator.
     #[payable]
     pub fn cancel_escrow(&mut self, escrow_id: String) -> Promise {
         assert_one_yocto();
@@ -290,45 +293,110 @@ impl Contract {
             .expect("Escrow not found")
             .clone();
 
+        if escrow.status == EscrowStatus::Created {
+            return self.internal_cancel_unfunded_escrow(escrow);
+        }
         require!(escrow.status == EscrowStatus::Funded, "Escrow not funded");
+        require!(!escrow.transfer_in_flight, "Escrow transfer in progress");
 
-        // Check if cancellation period has started
-        let current_stage = escrow.timelocks.get_current_stage(escrow.created_at);
-        require!(
-            matches!(current_stage, TimelockStage::Cancellation | TimelockStage::PublicCancellation),
-            "Not in cancellation period"
-        );
+        let caller = env::predecessor_account_id();
+        escrow.assert_can_cancel(&caller);
+
+        // Refund the parts not withdrawn yet
+        let (from, to) = (escrow.withdrawn_parts, escrow.parts());
+        let amount = escrow.parts_share(escrow.locked_amount(), from, to);
+        let safety_deposit = escrow.parts_share(escrow.safety_deposit, from, to);
 
-        // Update escrow
+        // Update escrow, `on_escrow_cancelled` restores it if the refund fails
         escrow.status = EscrowStatus::Cancelled;
+        escrow.transfer_in_flight = true;
 
         // Store updated escrow
         self.escrows.insert(escrow_id.clone(), escrow.clone());
 
-        // Return funds to taker
-        let transfer_promise = ext_ft::ext(escrow.taker_asset.clone())
+        // Return funds to the depositor
+        let receiver_id = escrow.depositor().clone();
+        ext_ft::ext(escrow.locked_asset().clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(GAS_FOR_FT_TRANSFER)
             .ft_transfer(
-                escrow.taker.clone(),
-                escrow.taking_amount,
+                receiver_id.clone(),
+                U128(amount),
                 Some(format!("Cancel escrow {}", escrow_id)),
-            );
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ESCROW_CALLBACK)
+                    .on_escrow_cancelled(EscrowCancelled {
+                        escrow_id: escrow_id.clone(),
+                        receiver: receiver_id,
+                        amount,
+                        safety_deposit,
+                        safety_deposit_receiver: caller,
+                    }),
+            )
+    }
 
-        // Emit event
-        EscrowCancelled {
-            escrow_id: escrow_id.clone(),
-            taker: escrow.taker,
+    /// Callback after the cancelled escrow was refunded. Pays the safety
+    /// deposit, or reopens the escrow if the refund failed.
+    #[private]
+    pub fn on_escrow_cancelled(
+        &mut self,
+        cancellation: EscrowCancelled,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> bool {
+        let mut escrow = self
+            .escrows
+            .get(&cancellation.escrow_id)
+            .expect("Escrow not found")
+            .clone();
+        escrow.transfer_in_flight = false;
+
+        if call_result.is_err() {
+            escrow.status = EscrowStatus::Funded;
+            self.escrows.insert(cancellation.escrow_id, escrow);
+            return false;
         }
-        .emit();
+        self.escrows.insert(cancellation.escrow_id.clone(), escrow);
 
-        transfer_promise
+        if cancellation.safety_deposit > 0 {
+            self.internal_pay_safety_deposit(
+                cancellation.safety_deposit_receiver.clone(),
+                cancellation.safety_deposit,
+            );
+        }
+        cancellation.emit();
+
+        true
     }
 
-    /// Callback after cancellation
+    /// Callback after a safety deposit was sent. A failed payout is kept for
+    /// its receiver to claim.
     #[private]
-    pub fn on_escrow_cancelled(&mut self, escrow_id: String) {
-        // Escrow has been successfully cancelled
+    pub fn on_safety_deposit_paid(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) {
+        if call_result.is_err() {
+            *self.unpaid_safety_deposits.entry(receiver_id).or_default() += amount.0;
+        }
+    }
+
+    /// Claim the safety deposits the caller could not be paid
+    pub fn claim_safety_deposit(&mut self) -> Promise {
+        let receiver_id = env::predecessor_account_id();
+        let amount = self
+            .unpaid_safety_deposits
+            .remove(&receiver_id)
+            .expect("No safety deposit to claim");
+        self.internal_pay_safety_deposit(receiver_id, amount)
+    }
+
+    /// Hash a 32-byte hex secret as an escrow with `hashlock` would
+    pub fn hash_secret(&self, secret: String, hashlock: HashlockAlgorithm) -> String {
+        format!("0x{}", hex::encode(hashlock.hash(&parse_bytes32(&secret))))
     }
 
     /// Get escrow by ID
@@ -336,6 +404,11 @@ impl Contract {
         self.escrows.get(&escrow_id).cloned()
     }
 
+    /// Safety deposit `account_id` can claim after a failed payout
+    pub fn get_unpaid_safety_deposit(&self, account_id: AccountId) -> U128 {
+        U128(self.unpaid_safety_deposits.get(&account_id).copied().unwrap_or(0))
+    }
+
     /// Get escrows by maker
     pub fn get_escrows_by_maker(&self, maker: AccountId) -> Vec<Escrow> {
         self.escrow_by_maker
@@ -386,6 +459,7 @@ impl Contract {
 impl Contract {
     fn generate_escrow_id(
         &self,
+        kind: &EscrowKind,
         maker_asset: &AccountId,
         taker_asset: &AccountId,
         making_amount: u128,
@@ -394,10 +468,29 @@ impl Contract {
         taker: &AccountId,
         source_chain: &str,
         destination_chain: &str,
+        timelocks: &Timelocks,
+        hashlock: &HashlockAlgorithm,
+        secret_parts: Option<u32>,
+        creator: &AccountId,
     ) -> String {
         let data = format!(
-            "{}:{}:{}:{}:{}:{}:{}:{}",
-            maker_asset, taker_asset, making_amount, taking_amount, maker, taker, source_chain, destination_chain
+            "{:?}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{:?}:{:?}:{}",
+            kind,
+            maker_asset,
+            taker_asset,
+            making_amount,
+            taking_amount,
+            maker,
+            taker,
+            source_chain,
+            destination_chain,
+            timelocks.withdrawal,
+            timelocks.public_withdrawal,
+            timelocks.cancellation,
+            timelocks.public_cancellation,
+            hashlock,
+            secret_parts,
+            creator
         );
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
@@ -405,14 +498,6 @@ impl Contract {
         format!("{:x}", hasher.finalize())
     }
 
-    fn verify_secret(&self, secret: &str, secret_hash: &str) -> bool {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(secret.as_bytes());
-        let computed_hash = format!("{:x}", hasher.finalize());
-        computed_hash == *secret_hash
-    }
-
     /// Generate a hash for escrow verification
     pub fn hash_escrow_data(&self, data: String) -> String {
         use sha2::{Digest, Sha256};
@@ -420,12 +505,4 @@ impl Contract {
         hasher.update(data.as_bytes());
         format!("{:x}", hasher.finalize())
     }
-
-    /// Generate a hash for secret verification
-    pub fn hash_secret(&self, secret: String) -> String {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(secret.as_bytes());
-        format!("{:x}", hasher.finalize())
-    }
 } 
\ No newline at end of file
@@ -1,16 +1,21 @@
 use near_sdk::{
     borsh::{BorshDeserialize, BorshSerialize},
+    require,
     serde::{Deserialize, Serialize},
     Timestamp,
 };
 
+use crate::types::EscrowKind;
+
+/// Start of each stage in nanoseconds after the escrow is funded, as in the
+/// 1inch cross-chain swap timelocks. A stage lasts until the next one starts
+/// and the last stage never ends.
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
 pub struct Timelocks {
-    pub finality: u64,           // Time for finality
-    pub withdrawal: u64,          // Time for withdrawal
-    pub public_withdrawal: u64,   // Time for public withdrawal
-    pub cancellation: u64,        // Time for cancellation
-    pub public_cancellation: u64, // Time for public cancellation
+    pub withdrawal: u64,          // Start of the taker only withdrawal
+    pub public_withdrawal: u64,   // Start of the public withdrawal
+    pub cancellation: u64,        // Start of the taker only cancellation
+    pub public_cancellation: u64, // Start of the public cancellation, source escrows only
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -20,60 +25,53 @@ pub enum TimelockStage {
     PublicWithdrawal,
     Cancellation,
     PublicCancellation,
-    Expired,
 }
 
 impl Timelocks {
     pub fn new(
-        finality: u64,
         withdrawal: u64,
         public_withdrawal: u64,
         cancellation: u64,
         public_cancellation: u64,
     ) -> Self {
         Self {
-            finality,
             withdrawal,
             public_withdrawal,
-            public_cancellation,
             cancellation,
+            public_cancellation,
+        }
+    }
+
+    pub fn assert_valid(&self, kind: &EscrowKind) {
+        require!(
+            self.withdrawal <= self.public_withdrawal
+                && self.public_withdrawal <= self.cancellation,
+            "Timelock stages must be in order"
+        );
+        if *kind == EscrowKind::Src {
+            require!(
+                self.cancellation <= self.public_cancellation,
+                "Timelock stages must be in order"
+            );
         }
     }
 
-    pub fn get_current_stage(&self, created_at: Timestamp) -> TimelockStage {
+    /// Stage of an escrow of `kind` funded at `funded_at`. Destination
+    /// escrows have no public cancellation, only the taker cancels them.
+    pub fn get_current_stage(&self, kind: &EscrowKind, funded_at: Timestamp) -> TimelockStage {
         let now = near_sdk::env::block_timestamp();
-        let elapsed = now - created_at;
+        let elapsed = now.saturating_sub(funded_at);
 
-        if elapsed < self.finality {
+        if elapsed < self.withdrawal {
             TimelockStage::Finality
-        } else if elapsed < self.withdrawal {
-            TimelockStage::Withdrawal
         } else if elapsed < self.public_withdrawal {
-            TimelockStage::PublicWithdrawal
+            TimelockStage::Withdrawal
         } else if elapsed < self.cancellation {
+            TimelockStage::PublicWithdrawal
+        } else if *kind == EscrowKind::Dst || elapsed < self.public_cancellation {
             TimelockStage::Cancellation
-        } else if elapsed < self.public_cancellation {
-            TimelockStage::PublicCancellation
         } else {
-            TimelockStage::Expired
+            TimelockStage::PublicCancellation
         }
     }
-
-    pub fn is_expired(&self) -> bool {
-        // This would need to be called with the escrow's created_at timestamp
-        // For now, return false - this should be implemented in the Escrow struct
-        false
-    }
-
-    pub fn can_withdraw(&self) -> bool {
-        // This would need to be called with the escrow's created_at timestamp
-        // For now, return true - this should be implemented in the Escrow struct
-        true
-    }
-
-    pub fn can_cancel(&self) -> bool {
-        // This would need to be called with the escrow's created_at timestamp
-        // For now, return true - this should be implemented in the Escrow struct
-        true
-    }
-} 
\ No newline at end of file
+}
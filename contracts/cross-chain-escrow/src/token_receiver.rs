@@ -0,0 +1,50 @@
+use near_sdk::{json_types::U128, near, AccountId, PromiseOrValue};
+
+use crate::*;
+
+const ERR_MALFORMED_MESSAGE: &str = "Invalid transfer action message";
+
+#[near(serializers = [json])]
+#[serde(rename_all = "snake_case")]
+enum TokenReceiverMessage {
+    /// Fund an escrow of the sender with the transferred locked asset
+    Fund {
+        escrow_id: String,
+        secret_hash: String,
+    },
+}
+
+#[near]
+impl Contract {
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        if msg.is_empty() {
+            // refund all
+            return PromiseOrValue::Value(amount);
+        }
+
+        let token_id = env::predecessor_account_id();
+        let message =
+            serde_json::from_str::<TokenReceiverMessage>(&msg).expect(ERR_MALFORMED_MESSAGE);
+        match message {
+            TokenReceiverMessage::Fund {
+                escrow_id,
+                secret_hash,
+            } => {
+                let used = self.internal_fund_escrow(
+                    &escrow_id,
+                    &token_id,
+                    &sender_id,
+                    amount.0,
+                    secret_hash,
+                );
+                // refund the amount exceeding the escrow amount
+                PromiseOrValue::Value(U128(amount.0 - used))
+            }
+        }
+    }
+}
@@ -4,22 +4,54 @@ use near_sdk::{
     AccountId, Timestamp,
 };
 
-use crate::timelocks::Timelocks;
+use crate::timelocks::{TimelockStage, Timelocks};
 
-#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
 pub struct Escrow {
     pub id: String,
+    pub kind: EscrowKind,
     pub maker: AccountId,
     pub taker: AccountId,
     pub maker_asset: AccountId,
     pub taker_asset: AccountId,
     pub making_amount: u128,
     pub taking_amount: u128,
+    pub hashlock: HashlockAlgorithm,
+    /// Hash of the secret, or the Merkle root of the parts' secret hashes
     pub secret_hash: String,
+    /// Number of parts withdrawn one by one with their own secret, when the
+    /// escrow is locked with a Merkle tree of secrets
+    pub secret_parts: Option<u32>,
+    pub withdrawn_parts: u32,
     pub timelocks: Timelocks,
     pub status: EscrowStatus,
     pub created_at: Timestamp,
     pub funded_at: Option<Timestamp>,
+    /// NEAR paid to whoever withdraws or cancels the escrow
+    pub safety_deposit: u128,
+    /// A withdrawal or cancellation is waiting for its transfer to complete
+    pub transfer_in_flight: bool,
+    /// Account that created the escrow and paid its safety deposit
+    pub creator: AccountId,
+}
+
+/// Hash of the secret an escrow is locked with. EVM escrows use
+/// `keccak256(bytes32)`, so the same secret unlocks both sides of a swap.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum HashlockAlgorithm {
+    Sha256,
+    Keccak256,
+}
+
+/// Side of a cross-chain swap an escrow is on, as 1inch EscrowSrc and EscrowDst
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum EscrowKind {
+    /// The maker locks `making_amount` of the maker asset, the taker
+    /// withdraws it with the secret, cancelling refunds the maker
+    Src,
+    /// The taker (resolver) locks `taking_amount` of the taker asset, the
+    /// maker receives it on withdrawal, cancelling refunds the taker
+    Dst,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -33,29 +65,75 @@ pub enum EscrowStatus {
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
 pub struct EscrowInfo {
     pub id: String,
+    pub kind: EscrowKind,
     pub maker: AccountId,
     pub taker: AccountId,
     pub maker_asset: AccountId,
     pub taker_asset: AccountId,
     pub making_amount: u128,
     pub taking_amount: u128,
+    pub hashlock: HashlockAlgorithm,
     pub secret_hash: String,
+    pub secret_parts: Option<u32>,
+    pub withdrawn_parts: u32,
     pub timelocks: Timelocks,
     pub status: EscrowStatus,
     pub created_at: Timestamp,
     pub funded_at: Option<Timestamp>,
+    /// NEAR paid to whoever withdraws or cancels the escrow
+    pub safety_deposit: u128,
 }
 
 impl Escrow {
-    pub fn is_expired(&self) -> bool {
-        self.timelocks.is_expired()
+    /// Account locking the escrowed funds and refunded on cancellation
+    pub fn depositor(&self) -> &AccountId {
+        match self.kind {
+            EscrowKind::Src => &self.maker,
+            EscrowKind::Dst => &self.taker,
+        }
+    }
+
+    /// Account receiving the escrowed funds on withdrawal
+    pub fn beneficiary(&self) -> &AccountId {
+        match self.kind {
+            EscrowKind::Src => &self.taker,
+            EscrowKind::Dst => &self.maker,
+        }
     }
 
-    pub fn can_withdraw(&self) -> bool {
-        self.timelocks.can_withdraw()
+    pub fn locked_asset(&self) -> &AccountId {
+        match self.kind {
+            EscrowKind::Src => &self.maker_asset,
+            EscrowKind::Dst => &self.taker_asset,
+        }
     }
 
-    pub fn can_cancel(&self) -> bool {
-        self.timelocks.can_cancel()
+    pub fn locked_amount(&self) -> u128 {
+        match self.kind {
+            EscrowKind::Src => self.making_amount,
+            EscrowKind::Dst => self.taking_amount,
+        }
     }
-} 
\ No newline at end of file
+
+    /// Number of secrets the escrow is withdrawn with
+    pub fn parts(&self) -> u32 {
+        self.secret_parts.unwrap_or(1)
+    }
+
+    /// Share of `total` belonging to parts `from..to`
+    pub fn parts_share(&self, total: u128, from: u32, to: u32) -> u128 {
+        self.parts_share_until(total, to) - self.parts_share_until(total, from)
+    }
+
+    /// `total * parts / self.parts()` rounded down, split so it cannot
+    /// overflow: the remainder times the parts fits in 64 bits
+    fn parts_share_until(&self, total: u128, parts: u32) -> u128 {
+        let all_parts = self.parts() as u128;
+        total / all_parts * parts as u128 + total % all_parts * parts as u128 / all_parts
+    }
+
+    pub fn current_stage(&self) -> TimelockStage {
+        let funded_at = self.funded_at.expect("Escrow not funded");
+        self.timelocks.get_current_stage(&self.kind, funded_at)
+    }
+}
@@ -0,0 +1,106 @@
+use near_gas::NearGas;
+use near_sdk::NearToken;
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const HOUR: u64 = 3_600_000_000_000;
+
+const SECRET: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+const SECRET_KECCAK256: &str = "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6";
+
+#[tokio::test]
+async fn test_failed_withdrawal_restores_escrow() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let unregistered = create_account(&sandbox, "unregistered", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc],
+        &[maker.id(), resolver.id(), cross_chain_escrow.id()],
+    )
+    .await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+
+    let escrow_id = create_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        json!({
+            "kind": "Src",
+            "maker_asset": usdc.id(),
+            "taker_asset": usdt.id(),
+            "making_amount": MAKING_AMOUNT,
+            "taking_amount": TAKING_AMOUNT,
+            "maker": maker.id(),
+            "taker": resolver.id(),
+            "source_chain": "near",
+            "destination_chain": "ethereum",
+            "timelocks": {
+                "withdrawal": 0,
+                "public_withdrawal": HOUR,
+                "cancellation": 2 * HOUR,
+                "public_cancellation": 3 * HOUR
+            },
+            "hashlock": "Keccak256"
+        }),
+        NearToken::from_near(1),
+    )
+    .await?;
+
+    let result = fund_escrow(
+        &cross_chain_escrow,
+        &maker,
+        &usdc,
+        &escrow_id,
+        MAKING_AMOUNT,
+        SECRET_KECCAK256,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // The receiver is not registered with the token, so the transfer fails
+    let result = resolver
+        .call(cross_chain_escrow.id(), "withdraw_from_escrow")
+        .args_json(json!({
+            "escrow_id": escrow_id,
+            "secret": SECRET,
+            "receiver": unregistered.id()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(NearGas::from_tgas(100))
+        .transact()
+        .await?;
+    assert!(!result.json::<bool>()?);
+
+    let escrow: serde_json::Value = cross_chain_escrow
+        .view("get_escrow")
+        .args_json(json!({ "escrow_id": escrow_id }))
+        .await?
+        .json()?;
+    assert_eq!(escrow["status"], json!("Funded"));
+    assert_eq!(escrow["withdrawn_parts"], json!(0));
+    assert_eq!(escrow["transfer_in_flight"], json!(false));
+    assert_eq!(
+        ft_balance_of(&usdc, cross_chain_escrow.id()).await?,
+        MAKING_AMOUNT
+    );
+
+    // The escrow can still be withdrawn to a registered account
+    let result = withdraw_from_escrow(&cross_chain_escrow, &resolver, &escrow_id, SECRET).await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert!(result.json::<bool>()?);
+    assert_eq!(ft_balance_of(&usdc, resolver.id()).await?, MAKING_AMOUNT);
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const HOUR: u64 = 3_600_000_000_000;
+
+// Cross-chain test vectors: a bytes32 secret and its hashes, as computed by
+// `keccak256(abi.encodePacked(secret))` on EVM and `sha256` of the 32 bytes
+const SECRET: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+const SECRET_KECCAK256: &str = "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6";
+const SECRET_SHA256: &str = "0xec4916dd28fc4c10d78e287ca5d9cc51ee1ae73cbfde08c6b37324cbfaac8bc5";
+
+#[tokio::test]
+async fn test_hash_secret_matches_evm_vectors() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    for (hashlock, hash) in [("Keccak256", SECRET_KECCAK256), ("Sha256", SECRET_SHA256)] {
+        let computed: String = cross_chain_escrow
+            .view("hash_secret")
+            .args_json(json!({ "secret": SECRET, "hashlock": hashlock }))
+            .await?
+            .json()?;
+        assert_eq!(computed, hash);
+    }
+
+    // Secrets without the prefix or in upper case are the same value
+    let computed: String = cross_chain_escrow
+        .view("hash_secret")
+        .args_json(json!({
+            "secret": SECRET.trim_start_matches("0x").to_uppercase(),
+            "hashlock": "Keccak256"
+        }))
+        .await?
+        .json()?;
+    assert_eq!(computed, SECRET_KECCAK256);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keccak_secret_unlocks_both_sides() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), resolver.id(), cross_chain_escrow.id()],
+    )
+    .await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &resolver, TAKING_AMOUNT).await?;
+
+    let mut escrow_ids = Vec::new();
+    for kind in ["Src", "Dst"] {
+        let escrow_id = create_escrow(
+            &cross_chain_escrow,
+            &resolver,
+            json!({
+                "kind": kind,
+                "maker_asset": usdc.id(),
+                "taker_asset": usdt.id(),
+                "making_amount": MAKING_AMOUNT,
+                "taking_amount": TAKING_AMOUNT,
+                "maker": maker.id(),
+                "taker": resolver.id(),
+                "source_chain": "near",
+                "destination_chain": "near",
+                "timelocks": {
+                    "withdrawal": 0,
+                    "public_withdrawal": HOUR,
+                    "cancellation": 2 * HOUR,
+                    "public_cancellation": 3 * HOUR
+                },
+                "hashlock": "Keccak256"
+            }),
+            NearToken::from_near(1),
+        )
+        .await?;
+        escrow_ids.push(escrow_id);
+    }
+
+    // The maker locks the source escrow, the resolver the destination one,
+    // both with the hash the EVM side uses
+    let result = fund_escrow(
+        &cross_chain_escrow,
+        &maker,
+        &usdc,
+        &escrow_ids[0],
+        MAKING_AMOUNT,
+        SECRET_KECCAK256,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    let result = fund_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        &usdt,
+        &escrow_ids[1],
+        TAKING_AMOUNT,
+        SECRET_KECCAK256,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // A wrong secret does not unlock the escrow
+    let wrong_secret = format!("0x{}", "00".repeat(31) + "02");
+    let result = withdraw_from_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        &escrow_ids[1],
+        &wrong_secret,
+    )
+    .await?;
+    assert!(result.is_failure());
+
+    // The secret revealed on one side unlocks the other
+    for escrow_id in escrow_ids.iter().rev() {
+        let result =
+            withdraw_from_escrow(&cross_chain_escrow, &resolver, escrow_id, SECRET).await?;
+        assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    }
+
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, TAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdc, resolver.id()).await?, MAKING_AMOUNT);
+
+    Ok(())
+}
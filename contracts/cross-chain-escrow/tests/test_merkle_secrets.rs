@@ -0,0 +1,186 @@
+use near_gas::NearGas;
+use near_sdk::NearToken;
+use near_workspaces::{result::ExecutionFinalResult, Account, Contract};
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const PARTS: u32 = 4;
+const HOUR: u64 = 3_600_000_000_000;
+
+// Merkle tree of the secrets 0x..01 to 0x..04 built as 1inch Fusion+ does:
+// leaves are keccak256(uint64 index ++ keccak256(secret)) and nodes hash their
+// sorted children. Computed with OpenSSL's Keccak-256, apart from the contract.
+const MERKLE_ROOT: &str = "0xad666143b53f264e10e8e7a6876abd9b19a2059c474c5d62e58140e832c92a5a";
+const MERKLE_PROOFS: [[&str; 2]; PARTS as usize] = [
+    [
+        "0x120960b212349df085965297827882d51bfc0f81d50ed273a9be5e58768e9990",
+        "0xb690db966526321a8bd65ff024a61bb160b350aa87d7972574f4103f2d760f64",
+    ],
+    [
+        "0xa0007178b07c67454652c6c218db198f4c8743bd4dbd6eeab07a4005f916db49",
+        "0xb690db966526321a8bd65ff024a61bb160b350aa87d7972574f4103f2d760f64",
+    ],
+    [
+        "0xbd3a5e4bc0cc94006099678c1c60adc351cb1fa3e1a04b02e9d6e6620dd39d83",
+        "0xcd272f367b36bb12543a94fcea857601d145b676c436ab46d87402c530d22add",
+    ],
+    [
+        "0xb48bff4a52f73b24ed840863b6c7b63792c514087983aaf69c4779df620d20c0",
+        "0xcd272f367b36bb12543a94fcea857601d145b676c436ab46d87402c530d22add",
+    ],
+];
+
+fn secret(index: u32) -> String {
+    format!("0x{:064x}", index + 1)
+}
+
+async fn withdraw_part(
+    cross_chain_escrow: &Contract,
+    caller: &Account,
+    escrow_id: &str,
+    index: u32,
+    proof: &[&str],
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = caller
+        .call(cross_chain_escrow.id(), "withdraw_from_escrow")
+        .args_json(json!({
+            "escrow_id": escrow_id,
+            "secret": secret(index),
+            "index": index,
+            "proof": proof
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(NearGas::from_tgas(100))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+#[tokio::test]
+async fn test_merkle_secrets_withdraw_in_parts() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc],
+        &[maker.id(), resolver.id(), cross_chain_escrow.id()],
+    )
+    .await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+
+    let escrow_id = create_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        json!({
+            "kind": "Src",
+            "maker_asset": usdc.id(),
+            "taker_asset": usdt.id(),
+            "making_amount": MAKING_AMOUNT,
+            "taking_amount": TAKING_AMOUNT,
+            "maker": maker.id(),
+            "taker": resolver.id(),
+            "source_chain": "near",
+            "destination_chain": "ethereum",
+            "timelocks": {
+                "withdrawal": 0,
+                "public_withdrawal": HOUR,
+                "cancellation": 2 * HOUR,
+                "public_cancellation": 3 * HOUR
+            },
+            "hashlock": "Keccak256",
+            "secret_parts": PARTS
+        }),
+        NearToken::from_near(1),
+    )
+    .await?;
+
+    let result = fund_escrow(
+        &cross_chain_escrow,
+        &maker,
+        &usdc,
+        &escrow_id,
+        MAKING_AMOUNT,
+        MERKLE_ROOT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // Secrets cannot be revealed ahead of the next part
+    let result = withdraw_part(
+        &cross_chain_escrow,
+        &resolver,
+        &escrow_id,
+        1,
+        &MERKLE_PROOFS[1],
+    )
+    .await?;
+    assert!(result.is_failure());
+
+    let result = withdraw_part(
+        &cross_chain_escrow,
+        &resolver,
+        &escrow_id,
+        0,
+        &MERKLE_PROOFS[0],
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert_eq!(
+        ft_balance_of(&usdc, resolver.id()).await?,
+        MAKING_AMOUNT / PARTS as u128
+    );
+
+    // A used index cannot be withdrawn again
+    let result = withdraw_part(
+        &cross_chain_escrow,
+        &resolver,
+        &escrow_id,
+        0,
+        &MERKLE_PROOFS[0],
+    )
+    .await?;
+    assert!(result.is_failure());
+
+    let escrow: serde_json::Value = cross_chain_escrow
+        .view("get_escrow")
+        .args_json(json!({ "escrow_id": escrow_id }))
+        .await?
+        .json()?;
+    assert_eq!(escrow["withdrawn_parts"], json!(1));
+    assert_eq!(escrow["status"], json!("Funded"));
+
+    for index in 1..PARTS {
+        let result = withdraw_part(
+            &cross_chain_escrow,
+            &resolver,
+            &escrow_id,
+            index,
+            &MERKLE_PROOFS[index as usize],
+        )
+        .await?;
+        assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    }
+    assert_eq!(ft_balance_of(&usdc, resolver.id()).await?, MAKING_AMOUNT);
+
+    let escrow: serde_json::Value = cross_chain_escrow
+        .view("get_escrow")
+        .args_json(json!({ "escrow_id": escrow_id }))
+        .await?
+        .json()?;
+    assert_eq!(escrow["status"], json!("Withdrawn"));
+
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use near_sdk::NearToken;
+use near_workspaces::{result::ExecutionFinalResult, Account, Contract};
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const SECOND: u64 = 1_000_000_000;
+
+const SECRET: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+const SECRET_KECCAK256: &str = "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6";
+
+fn escrow_args(
+    maker: &Account,
+    resolver: &Account,
+    usdc: &Contract,
+    usdt: &Contract,
+    timelocks: Value,
+) -> Value {
+    json!({
+        "kind": "Src",
+        "maker_asset": usdc.id(),
+        "taker_asset": usdt.id(),
+        "making_amount": MAKING_AMOUNT,
+        "taking_amount": TAKING_AMOUNT,
+        "maker": maker.id(),
+        "taker": resolver.id(),
+        "source_chain": "near",
+        "destination_chain": "ethereum",
+        "timelocks": timelocks,
+        "hashlock": "Keccak256"
+    })
+}
+
+async fn cancel_escrow(
+    cross_chain_escrow: &Contract,
+    caller: &Account,
+    escrow_id: &str,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = caller
+        .call(cross_chain_escrow.id(), "cancel_escrow")
+        .args_json(json!({ "escrow_id": escrow_id }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+async fn near_balance(account: &Account) -> Result<u128, Box<dyn std::error::Error>> {
+    Ok(account.view_account().await?.balance.as_yoctonear())
+}
+
+#[tokio::test]
+async fn test_only_taker_creates_escrow() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    let timelocks = json!({
+        "withdrawal": 0,
+        "public_withdrawal": SECOND,
+        "cancellation": 2 * SECOND,
+        "public_cancellation": 3 * SECOND
+    });
+    let result = maker
+        .call(cross_chain_escrow.id(), "create_escrow")
+        .args_json(escrow_args(&maker, &resolver, &usdc, &usdt, timelocks))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(result.is_failure());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_public_withdrawal_pays_safety_deposit() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let executor = create_account(&sandbox, "executor", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc],
+        &[maker.id(), resolver.id(), cross_chain_escrow.id()],
+    )
+    .await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+
+    let timelocks = json!({
+        "withdrawal": 0,
+        "public_withdrawal": 10 * SECOND,
+        "cancellation": 1_000 * SECOND,
+        "public_cancellation": 2_000 * SECOND
+    });
+    let escrow_id = create_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        escrow_args(&maker, &resolver, &usdc, &usdt, timelocks),
+        NearToken::from_near(1),
+    )
+    .await?;
+    let result = fund_escrow(
+        &cross_chain_escrow,
+        &maker,
+        &usdc,
+        &escrow_id,
+        MAKING_AMOUNT,
+        SECRET_KECCAK256,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // Only the taker withdraws before the public withdrawal
+    let result = withdraw_from_escrow(&cross_chain_escrow, &executor, &escrow_id, SECRET).await?;
+    assert!(result.is_failure());
+
+    sandbox.fast_forward(100).await?;
+
+    // Anyone withdraws to the taker then, and earns the safety deposit
+    let balance_before = near_balance(&executor).await?;
+    let result = withdraw_from_escrow(&cross_chain_escrow, &executor, &escrow_id, SECRET).await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert!(result.json::<bool>()?);
+
+    assert_eq!(ft_balance_of(&usdc, resolver.id()).await?, MAKING_AMOUNT);
+    assert!(
+        near_balance(&executor).await?
+            > balance_before + NearToken::from_millinear(990).as_yoctonear()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancel_refunds_depositor() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    register_accounts(&[&usdc], &[maker.id(), cross_chain_escrow.id()]).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+
+    let timelocks = json!({
+        "withdrawal": 0,
+        "public_withdrawal": 10 * SECOND,
+        "cancellation": 20 * SECOND,
+        "public_cancellation": 1_000 * SECOND
+    });
+    let escrow_id = create_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        escrow_args(&maker, &resolver, &usdc, &usdt, timelocks),
+        NearToken::from_near(1),
+    )
+    .await?;
+    let result = fund_escrow(
+        &cross_chain_escrow,
+        &maker,
+        &usdc,
+        &escrow_id,
+        MAKING_AMOUNT,
+        SECRET_KECCAK256,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // Nothing can be cancelled during the withdrawal
+    let result = cancel_escrow(&cross_chain_escrow, &resolver, &escrow_id).await?;
+    assert!(result.is_failure());
+
+    sandbox.fast_forward(100).await?;
+
+    // Only the taker cancels before the public cancellation
+    let result = cancel_escrow(&cross_chain_escrow, &maker, &escrow_id).await?;
+    assert!(result.is_failure());
+
+    let balance_before = near_balance(&resolver).await?;
+    let result = cancel_escrow(&cross_chain_escrow, &resolver, &escrow_id).await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert!(result.json::<bool>()?);
+
+    assert_eq!(ft_balance_of(&usdc, maker.id()).await?, MAKING_AMOUNT);
+    assert!(
+        near_balance(&resolver).await?
+            > balance_before + NearToken::from_millinear(990).as_yoctonear()
+    );
+
+    let escrow: Value = cross_chain_escrow
+        .view("get_escrow")
+        .args_json(json!({ "escrow_id": escrow_id }))
+        .await?
+        .json()?;
+    assert_eq!(escrow["status"], json!("Cancelled"));
+
+    // The secret no longer unlocks the escrow
+    let result = withdraw_from_escrow(&cross_chain_escrow, &resolver, &escrow_id, SECRET).await?;
+    assert!(result.is_failure());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancel_unfunded_escrow_returns_safety_deposit(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let resolver = create_account(&sandbox, "resolver", 10).await?;
+    let cross_chain_escrow = deploy_cross_chain_escrow(&sandbox, &owner).await?;
+
+    let timelocks = json!({
+        "withdrawal": 0,
+        "public_withdrawal": 10 * SECOND,
+        "cancellation": 20 * SECOND,
+        "public_cancellation": 30 * SECOND
+    });
+    let escrow_id = create_escrow(
+        &cross_chain_escrow,
+        &resolver,
+        escrow_args(&maker, &resolver, &usdc, &usdt, timelocks),
+        NearToken::from_near(1),
+    )
+    .await?;
+
+    // The safety deposit stays locked until the cancellation time
+    let result = cancel_escrow(&cross_chain_escrow, &resolver, &escrow_id).await?;
+    assert!(result.is_failure());
+
+    sandbox.fast_forward(100).await?;
+
+    let balance_before = near_balance(&resolver).await?;
+    let result = cancel_escrow(&cross_chain_escrow, &maker, &escrow_id).await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert!(
+        near_balance(&resolver).await? >= balance_before + NearToken::from_near(1).as_yoctonear()
+    );
+
+    let escrow: Value = cross_chain_escrow
+        .view("get_escrow")
+        .args_json(json!({ "escrow_id": escrow_id }))
+        .await?
+        .json()?;
+    assert_eq!(escrow["status"], json!("Cancelled"));
+
+    // A cancelled escrow cannot be funded
+    register_accounts(&[&usdc], &[maker.id(), cross_chain_escrow.id()]).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = fund_escrow(
+        &cross_chain_escrow,
+        &maker,
+        &usdc,
+        &escrow_id,
+        MAKING_AMOUNT,
+        SECRET_KECCAK256,
+    )
+    .await?;
+    assert_eq!(ft_balance_of(&usdc, maker.id()).await?, MAKING_AMOUNT);
+
+    Ok(())
+}
@@ -0,0 +1,260 @@
+#![allow(dead_code)]
+
+use near_contract_standards::fungible_token::Balance;
+use near_gas::NearGas;
+use near_sdk::{AccountId, NearToken};
+use near_workspaces::{network::Sandbox, result::ExecutionFinalResult, Account, Contract, Worker};
+use serde_json::{json, Value};
+
+pub const CROSS_CHAIN_ESCROW_CONTRACT_WASM: &str =
+    "../../target/near/cross_chain_escrow/cross_chain_escrow.wasm";
+pub const MOCK_FT_CONTRACT_WASM: &str = "../../target/near/mock_ft/mock_ft.wasm";
+
+pub async fn create_account(
+    sandbox: &Worker<Sandbox>,
+    prefix: &str,
+    balance: Balance,
+) -> Result<Account, Box<dyn std::error::Error>> {
+    let root = sandbox.root_account().unwrap();
+    Ok(root
+        .create_subaccount(prefix)
+        .initial_balance(NearToken::from_near(balance))
+        .transact()
+        .await?
+        .result)
+}
+
+pub async fn create_ft(
+    sandbox: &Worker<Sandbox>,
+    name: &str,
+    symbol: &str,
+    decimals: u32,
+    total_supply: Balance,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    let mock_ft_contract_wasm =
+        std::fs::read(MOCK_FT_CONTRACT_WASM).expect("Contract wasm not found");
+
+    let ft_account = create_account(sandbox, symbol.to_lowercase().as_str(), 100).await?;
+    let ft_contract = ft_account.deploy(&mock_ft_contract_wasm).await?.result;
+    let result = ft_contract
+        .call("new")
+        .args_json(json!({
+            "owner_id": ft_contract.id(),
+            "total_supply": total_supply.to_string(),
+            "metadata": {
+                "spec": "ft-1.0.0".to_string(),
+                "name": name.to_string(),
+                "symbol": symbol.to_string(),
+                "icon": None::<String>,
+                "reference": None::<String>,
+                "reference_hash": None::<String>,
+                "decimals": decimals,
+            }
+        }))
+        .transact()
+        .await?;
+    assert!(
+        result.is_success(),
+        "{:#?}",
+        result.into_result().unwrap_err()
+    );
+
+    Ok(ft_contract)
+}
+
+pub async fn storage_deposit(
+    ft: &Contract,
+    account_id: &AccountId,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = ft
+        .call("storage_deposit")
+        .args_json(json!({
+            "account_id": account_id,
+            "registration_only": true
+        }))
+        .deposit(NearToken::from_millinear(1250))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn ft_transfer(
+    ft: &Contract,
+    sender: &Account,
+    receiver: &Account,
+    amount: Balance,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = sender
+        .call(ft.id(), "ft_transfer")
+        .args_json(json!({
+            "receiver_id": receiver.id(),
+            "amount": amount.to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn ft_transfer_call(
+    ft: &Contract,
+    sender: &Account,
+    receiver_id: &AccountId,
+    amount: Balance,
+    msg: Value,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = sender
+        .call(ft.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": receiver_id,
+            "amount": amount.to_string(),
+            "msg": msg.to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(NearGas::from_tgas(300))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn ft_balance_of(
+    ft: &Contract,
+    account_id: &AccountId,
+) -> Result<Balance, Box<dyn std::error::Error>> {
+    let balance: String = ft
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": account_id }))
+        .await?
+        .json()?;
+
+    Ok(balance.parse()?)
+}
+
+pub async fn create_usdc(
+    sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    create_ft(sandbox, "USD Coin", "USDC", 6, 10_000_000_000_000_000).await
+}
+
+pub async fn create_usdt(
+    sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    create_ft(sandbox, "Tether USD", "USDT", 6, 10_000_000_000_000_000).await
+}
+
+/// Register every account with every token
+pub async fn register_accounts(
+    fts: &[&Contract],
+    account_ids: &[&AccountId],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for ft in fts {
+        for account_id in account_ids {
+            let result = storage_deposit(ft, account_id).await?;
+            assert!(result.is_success(), "{:#?}", result.failures());
+        }
+    }
+
+    Ok(())
+}
+
+/// Send `amount` of `ft` to `receiver` from the token's own supply
+pub async fn mint(
+    ft: &Contract,
+    receiver: &Account,
+    amount: Balance,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    ft_transfer(ft, ft.as_account(), receiver, amount).await
+}
+
+pub async fn deploy_cross_chain_escrow(
+    sandbox: &Worker<Sandbox>,
+    owner: &Account,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    let cross_chain_escrow_contract_wasm =
+        std::fs::read(CROSS_CHAIN_ESCROW_CONTRACT_WASM).expect("Contract wasm not found");
+    let cross_chain_escrow_account = create_account(sandbox, "escrow", 100).await?;
+    let cross_chain_escrow_contract = cross_chain_escrow_account
+        .deploy(&cross_chain_escrow_contract_wasm)
+        .await?
+        .result;
+
+    let result = cross_chain_escrow_contract
+        .call("new")
+        .args_json(json!({
+            "owner_id": owner.id(),
+            "limit_order_protocol_id": owner.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(
+        result.is_success(),
+        "{:#?}",
+        result.into_result().unwrap_err()
+    );
+
+    Ok(cross_chain_escrow_contract)
+}
+
+/// Create an escrow from `args`, attaching `safety_deposit`. Returns its id.
+pub async fn create_escrow(
+    cross_chain_escrow: &Contract,
+    resolver: &Account,
+    args: Value,
+    safety_deposit: NearToken,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let result = resolver
+        .call(cross_chain_escrow.id(), "create_escrow")
+        .args_json(args)
+        .deposit(safety_deposit)
+        .transact()
+        .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    Ok(result.json()?)
+}
+
+pub async fn fund_escrow(
+    cross_chain_escrow: &Contract,
+    depositor: &Account,
+    token: &Contract,
+    escrow_id: &str,
+    amount: Balance,
+    secret_hash: &str,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    ft_transfer_call(
+        token,
+        depositor,
+        cross_chain_escrow.id(),
+        amount,
+        json!({
+            "fund": {
+                "escrow_id": escrow_id,
+                "secret_hash": secret_hash
+            }
+        }),
+    )
+    .await
+}
+
+pub async fn withdraw_from_escrow(
+    cross_chain_escrow: &Contract,
+    caller: &Account,
+    escrow_id: &str,
+    secret: &str,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = caller
+        .call(cross_chain_escrow.id(), "withdraw_from_escrow")
+        .args_json(json!({
+            "escrow_id": escrow_id,
+            "secret": secret
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(NearGas::from_tgas(100))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
@@ -0,0 +1,78 @@
+use crate::*;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Set the solver registry used for orders restricted to registered solvers
+    pub fn set_solver_registry_id(&mut self, solver_registry_id: Option<AccountId>) {
+        self.assert_owner();
+        self.solver_registry_id = solver_registry_id;
+    }
+
+    /// Set the protocol fee, in basis points of the taker amount, and its recipient
+    pub fn set_protocol_fee(&mut self, fee_bps: u32, recipient: AccountId) {
+        self.assert_owner();
+        assert!(fee_bps <= MAX_FEE_BPS, "Protocol fee is too high");
+        self.protocol_fee_bps = fee_bps;
+        self.protocol_fee_recipient = recipient;
+    }
+
+    /// Set the wNEAR contract native NEAR orders are wrapped with
+    pub fn set_wnear_id(&mut self, wnear_id: Option<AccountId>) {
+        self.assert_owner();
+        self.wnear_id = wnear_id;
+    }
+
+    /// Allow predicates to make view calls to `oracle_id`
+    pub fn add_oracle(&mut self, oracle_id: AccountId) {
+        self.assert_owner();
+        assert!(
+            oracle_id != env::current_account_id(),
+            "Predicates cannot call the protocol"
+        );
+        self.oracles.insert(oracle_id);
+    }
+
+    /// Stop predicates from calling `oracle_id`. Orders already calling it can
+    /// no longer be filled.
+    pub fn remove_oracle(&mut self, oracle_id: AccountId) {
+        self.assert_owner();
+        self.oracles.remove(&oracle_id);
+    }
+
+    pub fn change_owner(&mut self, new_owner_id: AccountId) {
+        self.assert_owner();
+        self.owner_id = new_owner_id;
+    }
+
+    pub fn get_owner_id(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    pub fn get_solver_registry_id(&self) -> Option<AccountId> {
+        self.solver_registry_id.clone()
+    }
+
+    pub fn get_wnear_id(&self) -> Option<AccountId> {
+        self.wnear_id.clone()
+    }
+
+    pub fn get_oracles(&self) -> Vec<AccountId> {
+        self.oracles.iter().cloned().collect()
+    }
+
+    pub fn get_protocol_fee(&self) -> ProtocolFee {
+        ProtocolFee {
+            recipient: self.protocol_fee_recipient.clone(),
+            fee_bps: self.protocol_fee_bps,
+        }
+    }
+}
+
+impl LimitOrderProtocol {
+    pub(crate) fn assert_owner(&self) {
+        assert!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only owner can call this method"
+        );
+    }
+}
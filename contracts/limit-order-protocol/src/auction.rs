@@ -0,0 +1,174 @@
+use crate::math::{mul_div, Rounding};
+use crate::*;
+
+/// Rate bumps are expressed in units of 1 / 10^7, as in 1inch Fusion
+pub const RATE_BUMP_DENOMINATOR: u128 = 10_000_000;
+
+impl AuctionDetails {
+    pub fn assert_valid(&self) {
+        assert!(self.duration > 0, "Auction duration must be greater than 0");
+        let points_duration = self
+            .points
+            .iter()
+            .try_fold(0u64, |total, point| total.checked_add(point.delay))
+            .expect("Auction points overflow");
+        assert!(
+            points_duration <= self.duration,
+            "Auction points exceed the auction duration"
+        );
+    }
+
+    /// Rate bump at `now`, interpolated linearly between auction points
+    pub fn rate_bump(&self, now: Timestamp) -> u64 {
+        if now <= self.start_time {
+            return self.initial_rate_bump as u64;
+        }
+        let end_time = self.start_time + self.duration;
+        if now >= end_time {
+            return 0;
+        }
+
+        let mut from_time = self.start_time;
+        let mut from_bump = self.initial_rate_bump as u64;
+        for point in self.points.iter() {
+            let to_time = from_time + point.delay;
+            if now < to_time {
+                return interpolate(from_time, from_bump, to_time, point.rate_bump as u64, now);
+            }
+            from_time = to_time;
+            from_bump = point.rate_bump as u64;
+        }
+
+        interpolate(from_time, from_bump, end_time, 0, now)
+    }
+}
+
+fn interpolate(from_time: u64, from_bump: u64, to_time: u64, to_bump: u64, now: u64) -> u64 {
+    let span = (to_time - from_time) as u128;
+    let bump = (from_bump as u128 * (to_time - now) as u128
+        + to_bump as u128 * (now - from_time) as u128)
+        / span;
+    bump as u64
+}
+
+impl Order {
+    pub fn current_rate_bump(&self) -> u64 {
+        self.auction
+            .as_ref()
+            .map(|auction| auction.rate_bump(env::block_timestamp()))
+            .unwrap_or(0)
+    }
+
+    /// Taking amount for the whole order at the current block, rounded up in
+    /// the maker's favour
+    pub fn current_taking_amount(&self) -> u128 {
+        mul_div(
+            self.taking_amount,
+            RATE_BUMP_DENOMINATOR + self.current_rate_bump() as u128,
+            RATE_BUMP_DENOMINATOR,
+            Rounding::Up,
+        )
+    }
+}
+
+#[near]
+impl LimitOrderProtocol {
+    /// Get the current price of an order, including its auction rate bump
+    pub fn get_current_rate(&self, order_id: String) -> Option<OrderRate> {
+        self.orders.get(&order_id).map(|order| {
+            let (making_amount, taking_amount) =
+                order.fill_amounts(order.remaining_making_amount(), true);
+            OrderRate {
+                rate_bump: order.current_rate_bump(),
+                making_amount: U128(making_amount),
+                taking_amount: U128(taking_amount),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts at 1000 with a bump of 1000, falls to 600 by 1200, steps down to
+    /// 400, falls to 100 by 1500 and reaches 0 at 2000
+    fn auction() -> AuctionDetails {
+        AuctionDetails {
+            start_time: 1000,
+            duration: 1000,
+            initial_rate_bump: 1000,
+            points: vec![
+                AuctionPoint {
+                    delay: 200,
+                    rate_bump: 600,
+                },
+                AuctionPoint {
+                    delay: 0,
+                    rate_bump: 400,
+                },
+                AuctionPoint {
+                    delay: 300,
+                    rate_bump: 100,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate(0, 1000, 100, 0, 0), 1000);
+        assert_eq!(interpolate(0, 1000, 100, 0, 25), 750);
+        assert_eq!(interpolate(0, 1000, 100, 0, 100), 0);
+        assert_eq!(interpolate(0, 0, 100, 1000, 25), 250);
+    }
+
+    #[test]
+    fn test_interpolate_rounds_down() {
+        assert_eq!(interpolate(0, 1, 3, 0, 1), 0);
+        assert_eq!(interpolate(0, 2, 3, 0, 1), 1);
+    }
+
+    #[test]
+    fn test_rate_bump_before_start() {
+        let auction = auction();
+        assert_eq!(auction.rate_bump(0), 1000);
+        assert_eq!(auction.rate_bump(1000), 1000);
+    }
+
+    #[test]
+    fn test_rate_bump_at_points() {
+        let auction = auction();
+        // The zero-delay point replaces the one before it at the same time
+        assert_eq!(auction.rate_bump(1200), 400);
+        assert_eq!(auction.rate_bump(1500), 100);
+    }
+
+    #[test]
+    fn test_rate_bump_between_points() {
+        let auction = auction();
+        assert_eq!(auction.rate_bump(1100), 800);
+        assert_eq!(auction.rate_bump(1199), 602);
+        assert_eq!(auction.rate_bump(1350), 250);
+        assert_eq!(auction.rate_bump(1750), 50);
+    }
+
+    #[test]
+    fn test_rate_bump_after_end() {
+        let auction = auction();
+        assert_eq!(auction.rate_bump(1999), 0);
+        assert_eq!(auction.rate_bump(2000), 0);
+        assert_eq!(auction.rate_bump(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_rate_bump_without_points() {
+        let auction = AuctionDetails {
+            points: vec![],
+            ..auction()
+        };
+        assert_eq!(auction.rate_bump(1000), 1000);
+        assert_eq!(auction.rate_bump(1500), 500);
+        assert_eq!(auction.rate_bump(2000), 0);
+    }
+}
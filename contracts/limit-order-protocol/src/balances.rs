@@ -0,0 +1,189 @@
+use near_sdk::{assert_one_yocto, PromiseError};
+
+use crate::*;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Withdraw unlocked funds held by the protocol for the caller
+    #[payable]
+    pub fn withdraw(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        assert!(amount.0 > 0, "Amount must be greater than 0");
+
+        let account_id = env::predecessor_account_id();
+        self.internal_withdraw_balance(&account_id, &token_id, amount.0);
+        self.internal_send(&account_id, &token_id, amount.0, "Withdraw".to_string())
+    }
+
+    /// Callback after sending tokens out of the protocol
+    #[private]
+    pub fn on_transfer_out(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) {
+        if call_result.is_err() {
+            // Keep the funds claimable through `withdraw`
+            self.internal_deposit_balance(&account_id, &token_id, amount.0);
+        }
+    }
+
+    /// Callback after depositing tokens into a NEAR Intents contract. Whatever
+    /// the deposit did not use is credited to `account_id`.
+    #[private]
+    pub fn on_intents_deposit(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        #[callback_result] used_amount: Result<U128, PromiseError>,
+    ) {
+        let unused_amount = match used_amount {
+            Ok(used_amount) => amount.0.saturating_sub(used_amount.0),
+            Err(_) => amount.0,
+        };
+        if unused_amount > 0 {
+            self.internal_deposit_balance(&account_id, &token_id, unused_amount);
+        }
+    }
+
+    /// Get the unlocked balance of an account for a token
+    pub fn get_balance(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128(self.internal_balance_of(&account_id, &token_id))
+    }
+
+    /// Get all unlocked balances of an account
+    pub fn get_balances(&self, account_id: AccountId) -> Vec<(AccountId, U128)> {
+        self.balances
+            .get(&account_id)
+            .map(|balances| {
+                balances
+                    .iter()
+                    .map(|(token_id, amount)| (token_id.clone(), U128(*amount)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl LimitOrderProtocol {
+    pub(crate) fn internal_balance_of(&self, account_id: &AccountId, token_id: &AccountId) -> u128 {
+        self.balances
+            .get(account_id)
+            .and_then(|balances| balances.get(token_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Credit `amount` to the balance of `account_id`, charging a new balance
+    /// record to its storage balance
+    pub(crate) fn internal_deposit_balance(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: u128,
+    ) {
+        self.balances.flush();
+        let initial_usage = env::storage_usage();
+        let balance = self
+            .balances
+            .entry(account_id.clone())
+            .or_default()
+            .entry(token_id.clone())
+            .or_insert(0);
+        *balance = balance.checked_add(amount).expect("Balance overflow");
+
+        self.balances.flush();
+        self.internal_charge_storage(account_id, initial_usage);
+    }
+
+    pub(crate) fn internal_withdraw_balance(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: u128,
+    ) {
+        self.balances.flush();
+        let initial_usage = env::storage_usage();
+        let balances = self
+            .balances
+            .get_mut(account_id)
+            .expect("Not enough balance");
+        let balance = balances.get(token_id).copied().unwrap_or(0);
+        assert!(balance >= amount, "Not enough balance");
+
+        if balance == amount {
+            balances.remove(token_id);
+            if balances.is_empty() {
+                self.balances.remove(account_id);
+            }
+        } else {
+            balances.insert(token_id.clone(), balance - amount);
+        }
+
+        self.balances.flush();
+        self.internal_charge_storage(account_id, initial_usage);
+    }
+
+    /// Send the taker asset an order's maker earned to the order's receiver.
+    /// Whatever cannot be delivered is credited to the receiving account, or
+    /// to the maker for intents deposits.
+    pub(crate) fn internal_send_proceeds(
+        &self,
+        order: &Order,
+        amount: u128,
+        memo: String,
+    ) -> Promise {
+        match order.receiver.as_ref() {
+            None => self.internal_send(&order.maker, &order.taker_asset, amount, memo),
+            Some(Receiver::Account(account_id)) => {
+                self.internal_send(account_id, &order.taker_asset, amount, memo)
+            }
+            Some(Receiver::Intents { contract_id, msg }) => ext_ft::ext(order.taker_asset.clone())
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .with_static_gas(GAS_FOR_FT_TRANSFER_CALL)
+                .ft_transfer_call(contract_id.clone(), U128(amount), Some(memo), msg.clone())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_FT_TRANSFER_CALLBACK)
+                        .on_intents_deposit(
+                            order.maker.clone(),
+                            order.taker_asset.clone(),
+                            U128(amount),
+                        ),
+                ),
+        }
+    }
+
+    /// Transfer tokens held by the protocol to `account_id`. If the transfer fails
+    /// the amount is credited to the account's balance instead.
+    pub(crate) fn internal_send(
+        &self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: u128,
+        memo: String,
+    ) -> Promise {
+        ext_ft::ext(token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(account_id.clone(), U128(amount), Some(memo))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_FT_TRANSFER_CALLBACK)
+                    .on_transfer_out(account_id.clone(), token_id.clone(), U128(amount)),
+            )
+    }
+}
+
+impl Order {
+    /// Gas delivering the maker proceeds takes on top of a plain transfer
+    pub fn proceeds_gas(&self) -> Gas {
+        match self.receiver {
+            Some(Receiver::Intents { .. }) => GAS_FOR_FT_TRANSFER_CALL,
+            _ => Gas::from_gas(0),
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+
+use near_sdk::{assert_one_yocto, PromiseOrValue, PromiseResult};
+
+use crate::predicate::parse_view_result;
+use crate::*;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Fill several orders, paying from the caller's balance. Only the net
+    /// amount of each token is taken from the balance, so the maker asset
+    /// received from one fill can pay for another, and net proceeds are sent
+    /// to the caller. Returns whether the fills were settled: if any of them
+    /// cannot be, all of them are rolled back.
+    #[payable]
+    pub fn fill_orders(&mut self, fills: Vec<FillInstruction>) -> PromiseOrValue<bool> {
+        assert_one_yocto();
+        assert!(!fills.is_empty(), "No fills given");
+
+        let taker = env::predecessor_account_id();
+        let requests = fills
+            .into_iter()
+            .map(|fill| {
+                if let Some(signed_order) = fill.signed_order {
+                    self.internal_create_signed_order(&fill.order_id, signed_order);
+                }
+                let order = self.orders.get(&fill.order_id).expect("Order not found");
+                FillRequest {
+                    token_id: order.taker_asset.clone(),
+                    order_id: fill.order_id,
+                    taker: taker.clone(),
+                    amount: fill.amount,
+                    making_amount: fill.making_amount,
+                    threshold: fill.threshold,
+                    interaction: None,
+                    unwrap_near: false,
+                }
+            })
+            .collect();
+
+        self.internal_fill_batch(taker, requests)
+    }
+
+    /// Cross two orders that sell each other's taker asset. The caller fills
+    /// both without providing any tokens and receives the spread between their
+    /// prices. Returns whether the fills were settled, all or none of them.
+    pub fn match_orders(&mut self, order_id_a: String, order_id_b: String) -> PromiseOrValue<bool> {
+        let matcher = env::predecessor_account_id();
+        let order_a = self.orders.get(&order_id_a).expect("Order not found");
+        let order_b = self.orders.get(&order_id_b).expect("Order not found");
+        assert!(
+            order_a.maker_asset == order_b.taker_asset
+                && order_a.taker_asset == order_b.maker_asset,
+            "Orders are not on opposite sides of a pair"
+        );
+
+        // A sells B as much as B still buys, and is paid out of B's maker
+        // asset. Both fills are rounded in their maker's favour.
+        let (_, wanted_by_b) = order_b.fill_amounts(order_b.remaining_making_amount(), true);
+        let (amount, taker_amount_a) = order_a.fill_amounts(wanted_by_b, true);
+        let (maker_amount_b, _) = order_b.fill_amounts(amount, false);
+        assert!(maker_amount_b >= taker_amount_a, "Orders do not cross");
+
+        let requests = vec![
+            FillRequest {
+                order_id: order_id_a.clone(),
+                token_id: order_a.taker_asset.clone(),
+                taker: matcher.clone(),
+                amount: U128(taker_amount_a),
+                making_amount: Some(U128(amount)),
+                threshold: None,
+                interaction: None,
+                unwrap_near: false,
+            },
+            FillRequest {
+                order_id: order_id_b.clone(),
+                token_id: order_b.taker_asset.clone(),
+                taker: matcher.clone(),
+                amount: U128(amount),
+                making_amount: None,
+                threshold: None,
+                interaction: None,
+                unwrap_near: false,
+            },
+        ];
+
+        self.internal_fill_batch(matcher, requests)
+    }
+
+    /// Callback after the solver permission and the predicate view calls of a
+    /// batch returned
+    #[private]
+    pub fn on_fill_batch_checked(&mut self, batch: PendingBatch) -> PromiseOrValue<bool> {
+        let mut results = (0..env::promise_results_count()).map(|i| match env::promise_result(i) {
+            PromiseResult::Successful(data) => Some(data),
+            _ => None,
+        });
+
+        let is_solver = !self.internal_batch_needs_solver(&batch)
+            || results
+                .next()
+                .flatten()
+                .and_then(|data| serde_json::from_slice::<bool>(&data).ok())
+                == Some(true);
+        if !is_solver || !self.internal_batch_predicates_hold(&batch, &mut results) {
+            self.internal_revert_batch(&batch);
+            return PromiseOrValue::Value(false);
+        }
+
+        let interactions = self.internal_batch_interactions(&batch);
+        self.internal_run_batch_interactions(batch, interactions)
+    }
+
+    /// Callback after an interaction of a batch. Runs the next interaction, or
+    /// settles the batch once all of them succeeded.
+    #[private]
+    pub fn on_fill_batch_interaction(
+        &mut self,
+        batch: PendingBatch,
+        interactions: Vec<Interaction>,
+    ) -> PromiseOrValue<bool> {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            self.internal_revert_batch(&batch);
+            return PromiseOrValue::Value(false);
+        }
+
+        self.internal_run_batch_interactions(batch, interactions)
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Reserve every fill of a batch and take the taker's net payment from
+    /// their balance, then check the orders' conditions
+    fn internal_fill_batch(
+        &mut self,
+        taker: AccountId,
+        requests: Vec<FillRequest>,
+    ) -> PromiseOrValue<bool> {
+        let fills: Vec<PendingFill> = requests
+            .iter()
+            .map(|request| self.internal_reserve_fill(request))
+            .collect();
+
+        let (debited, _) = self.internal_batch_net_amounts(&fills);
+        for (token_id, amount) in debited.iter() {
+            self.internal_withdraw_balance(&taker, token_id, amount.0);
+        }
+
+        self.internal_check_batch(PendingBatch {
+            taker,
+            fills,
+            debited,
+        })
+    }
+
+    /// Check the solver permission and the predicates of a batch. Checks that
+    /// need view calls continue in `on_fill_batch_checked`.
+    fn internal_check_batch(&mut self, batch: PendingBatch) -> PromiseOrValue<bool> {
+        let mut checks = vec![];
+        if self.internal_batch_needs_solver(&batch) {
+            let solver_registry_id = self
+                .solver_registry_id
+                .clone()
+                .expect("Solver registry is not set");
+            checks.push(
+                ext_solver_registry::ext(solver_registry_id)
+                    .with_static_gas(GAS_FOR_SOLVER_CHECK)
+                    .has_solver_permission(batch.taker.clone(), SolverType::LimitOrder),
+            );
+        }
+        for fill in batch.fills.iter() {
+            let order = self.orders.get(&fill.order_id).expect("Order not found");
+            if let Some(predicate) = order.predicate.as_ref() {
+                let mut calls = vec![];
+                predicate.view_calls(&mut calls);
+                checks.extend(calls.into_iter().map(ViewCall::call));
+            }
+        }
+
+        let interactions = self.internal_batch_interactions(&batch);
+        let Some(checks) = checks
+            .into_iter()
+            .reduce(|joint, promise| joint.and(promise))
+        else {
+            assert!(
+                self.internal_batch_predicates_hold(&batch, &mut std::iter::empty()),
+                "{}",
+                FillRejection::PredicateNotSatisfied.message()
+            );
+            return self.internal_run_batch_interactions(batch, interactions);
+        };
+
+        let settle_gas = batch_interactions_gas(
+            &batch,
+            &interactions,
+            self.internal_batch_settle_gas(&batch),
+        );
+        checks
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_FILL_CHECK_CALLBACK.saturating_add(settle_gas))
+                    .on_fill_batch_checked(batch),
+            )
+            .into()
+    }
+
+    fn internal_batch_needs_solver(&self, batch: &PendingBatch) -> bool {
+        batch.fills.iter().any(|fill| {
+            self.orders
+                .get(&fill.order_id)
+                .expect("Order not found")
+                .solvers_only
+        })
+    }
+
+    /// Evaluate the predicates of every order in the batch, taking the results
+    /// of their view calls from `results` in order
+    fn internal_batch_predicates_hold(
+        &self,
+        batch: &PendingBatch,
+        results: &mut impl Iterator<Item = Option<Vec<u8>>>,
+    ) -> bool {
+        for fill in batch.fills.iter() {
+            let order = self.orders.get(&fill.order_id).expect("Order not found");
+            let Some(predicate) = order.predicate.as_ref() else {
+                continue;
+            };
+
+            let mut calls = vec![];
+            predicate.view_calls(&mut calls);
+            let view_results: Vec<Option<u128>> = calls
+                .iter()
+                .map(|call| {
+                    results
+                        .next()
+                        .flatten()
+                        .and_then(|data| parse_view_result(&data, call.field.as_deref()))
+                })
+                .collect();
+            if !self.evaluate_predicate(&order.maker, predicate, &mut view_results.into_iter()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Gas settling a batch takes on top of plain transfers: delivering the
+    /// maker proceeds and running the post interactions
+    fn internal_batch_settle_gas(&self, batch: &PendingBatch) -> Gas {
+        batch.fills.iter().fold(Gas::from_gas(0), |gas, fill| {
+            let order = self.orders.get(&fill.order_id).expect("Order not found");
+            gas.saturating_add(order.proceeds_gas())
+                .saturating_add(order.post_interaction_gas())
+        })
+    }
+
+    /// Interactions run before a batch is settled: the pre interaction of
+    /// every order in it. Post interactions run once the batch is settled.
+    fn internal_batch_interactions(&self, batch: &PendingBatch) -> Vec<Interaction> {
+        batch
+            .fills
+            .iter()
+            .filter_map(|fill| {
+                self.orders
+                    .get(&fill.order_id)
+                    .expect("Order not found")
+                    .pre_interaction
+                    .clone()
+            })
+            .collect()
+    }
+
+    /// Call the first of `interactions` and continue in its callback, or
+    /// settle the batch if there are none left. The batch is reverted if the
+    /// interaction is no longer allowed.
+    fn internal_run_batch_interactions(
+        &mut self,
+        batch: PendingBatch,
+        mut interactions: Vec<Interaction>,
+    ) -> PromiseOrValue<bool> {
+        if interactions.is_empty() {
+            self.internal_settle_batch(batch);
+            return PromiseOrValue::Value(true);
+        }
+
+        let interaction = interactions.remove(0);
+        if !self.is_interaction_allowed(&interaction) {
+            self.internal_revert_batch(&batch);
+            return PromiseOrValue::Value(false);
+        }
+
+        let settle_gas = self.internal_batch_settle_gas(&batch);
+        interaction
+            .call()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(batch_interactions_gas(&batch, &interactions, settle_gas))
+                    .on_fill_batch_interaction(batch, interactions),
+            )
+            .into()
+    }
+
+    /// Pay out every fill of the batch and send the taker their net proceeds.
+    /// Proceeds that cannot be transferred are credited to the taker's balance.
+    /// The post interaction of each order runs once its maker is paid.
+    fn internal_settle_batch(&mut self, batch: PendingBatch) {
+        let (_, proceeds) = self.internal_batch_net_amounts(&batch.fills);
+        for fill in batch.fills {
+            // Settling may remove the order, so keep it for the post interaction
+            let order = self
+                .orders
+                .get(&fill.order_id)
+                .expect("Order not found")
+                .clone();
+            let settlement = self.internal_settle_fill(fill);
+            // Nothing is refunded from a batch
+            let _ = self.internal_run_post_interaction(&order, settlement, U128(0));
+        }
+        for (token_id, amount) in proceeds {
+            self.internal_send(&batch.taker, &token_id, amount.0, "Fill orders".to_string());
+        }
+    }
+
+    fn internal_revert_batch(&mut self, batch: &PendingBatch) {
+        for fill in batch.fills.iter().rev() {
+            self.internal_revert_fill(fill);
+        }
+        for (token_id, amount) in batch.debited.iter() {
+            self.internal_deposit_balance(&batch.taker, token_id, amount.0);
+        }
+    }
+
+    /// Net amounts of each token the taker of `fills` pays and receives
+    fn internal_batch_net_amounts(
+        &self,
+        fills: &[PendingFill],
+    ) -> (Vec<(AccountId, U128)>, Vec<(AccountId, U128)>) {
+        // Amounts paid and received by token
+        let mut flows: BTreeMap<AccountId, (u128, u128)> = BTreeMap::new();
+        for fill in fills.iter() {
+            let order = self.orders.get(&fill.order_id).expect("Order not found");
+            flows.entry(order.taker_asset.clone()).or_default().0 += fill.taker_amount.0;
+            flows.entry(order.maker_asset.clone()).or_default().1 += fill.maker_amount.0;
+        }
+
+        let mut paid = vec![];
+        let mut received = vec![];
+        for (token_id, (paid_amount, received_amount)) in flows {
+            if paid_amount > received_amount {
+                paid.push((token_id, U128(paid_amount - received_amount)));
+            } else if received_amount > paid_amount {
+                received.push((token_id, U128(received_amount - paid_amount)));
+            }
+        }
+        (paid, received)
+    }
+}
+
+/// Gas for a callback that runs `interactions` and their callbacks, then
+/// settles `batch`, which takes `settle_gas` on top of plain transfers
+fn batch_interactions_gas(
+    batch: &PendingBatch,
+    interactions: &[Interaction],
+    settle_gas: Gas,
+) -> Gas {
+    interactions.iter().fold(
+        GAS_FOR_FILL_CALLBACK
+            .saturating_mul(batch.fills.len() as u64 + 1)
+            .saturating_add(settle_gas),
+        |gas, interaction| {
+            gas.saturating_add(interaction.gas)
+                .saturating_add(GAS_FOR_INTERACTION_CALLBACK)
+        },
+    )
+}
@@ -24,6 +24,9 @@ pub struct OrderFilled {
     pub taker_asset: AccountId,
     pub maker_amount: u128,
     pub taker_amount: u128,
+    pub protocol_fee: u128,
+    pub integrator_fee: u128,
+    pub integrator: Option<AccountId>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -31,6 +34,58 @@ pub struct OrderFilled {
 pub struct OrderCancelled {
     pub order_id: String,
     pub maker: AccountId,
+    pub refunded_amount: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderFunded {
+    pub order_id: String,
+    pub maker: AccountId,
+    pub amount: u128,
+    pub escrowed_amount: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderExpired {
+    pub order_id: String,
+    pub maker: AccountId,
+    pub refunded_amount: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderAmended {
+    pub order_id: String,
+    pub maker: AccountId,
+    pub making_amount: u128,
+    pub taking_amount: u128,
+    pub expiration: u64,
+    pub refunded_amount: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostInteractionFailed {
+    pub order_id: String,
+    pub maker: AccountId,
+    pub contract_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NoncesInvalidated {
+    pub maker: AccountId,
+    pub nonces: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EpochIncreased {
+    pub maker: AccountId,
+    pub series: u64,
+    pub epoch: u64,
 }
 
 pub trait Event {
@@ -53,4 +108,40 @@ impl Event for OrderCancelled {
     fn emit(&self) {
         near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
     }
-} 
\ No newline at end of file
+}
+
+impl Event for OrderFunded {
+    fn emit(&self) {
+        near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
+    }
+}
+
+impl Event for OrderExpired {
+    fn emit(&self) {
+        near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
+    }
+}
+
+impl Event for OrderAmended {
+    fn emit(&self) {
+        near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
+    }
+}
+
+impl Event for PostInteractionFailed {
+    fn emit(&self) {
+        near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
+    }
+}
+
+impl Event for NoncesInvalidated {
+    fn emit(&self) {
+        near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
+    }
+}
+
+impl Event for EpochIncreased {
+    fn emit(&self) {
+        near_sdk::env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(self).unwrap()));
+    }
+}
@@ -1,12 +1,26 @@
-use near_sdk::ext_contract;
+use near_sdk::{ext_contract, json_types::U128, AccountId, PromiseOrValue};
+
+use crate::types::SolverType;
 
 #[ext_contract(ext_ft)]
 pub trait FungibleToken {
-    fn ft_transfer(&mut self, receiver_id: near_sdk::AccountId, amount: u128, memo: Option<String>);
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
     fn ft_transfer_call(
         &mut self,
-        receiver_id: near_sdk::AccountId,
-        amount: u128,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
         msg: String,
-    ) -> near_sdk::Promise;
-} 
\ No newline at end of file
+    ) -> PromiseOrValue<U128>;
+}
+
+#[ext_contract(ext_wnear)]
+pub trait WrappedNear {
+    fn near_deposit(&mut self);
+    fn near_withdraw(&mut self, amount: U128);
+}
+
+#[ext_contract(ext_solver_registry)]
+pub trait SolverRegistry {
+    fn has_solver_permission(&self, worker_id: AccountId, solver_type: SolverType) -> bool;
+}
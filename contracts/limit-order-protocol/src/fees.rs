@@ -0,0 +1,79 @@
+use near_sdk::assert_one_yocto;
+
+use crate::math::{mul_div, Rounding};
+use crate::*;
+
+/// Fees are expressed in basis points
+pub const FEE_DENOMINATOR: u128 = 10_000;
+/// Upper bound of the protocol and integrator fees, 10% each
+pub const MAX_FEE_BPS: u32 = 1_000;
+
+/// Fee of `fee_bps` basis points on `amount`, rounded down
+pub fn fee_amount(amount: u128, fee_bps: u32) -> u128 {
+    mul_div(amount, fee_bps as u128, FEE_DENOMINATOR, Rounding::Down)
+}
+
+#[near]
+impl LimitOrderProtocol {
+    /// Withdraw all fees the caller collected in `token_id`
+    #[payable]
+    pub fn withdraw_fees(&mut self, token_id: AccountId) -> Promise {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        self.fee_balances.flush();
+        let initial_usage = env::storage_usage();
+        let fees = self
+            .fee_balances
+            .get_mut(&account_id)
+            .expect("No fees to withdraw");
+        let amount = fees.remove(&token_id).expect("No fees to withdraw");
+        if fees.is_empty() {
+            self.fee_balances.remove(&account_id);
+        }
+        self.fee_balances.flush();
+        self.internal_charge_storage(&account_id, initial_usage);
+
+        self.internal_send(&account_id, &token_id, amount, "Withdraw fees".to_string())
+    }
+
+    /// Get the fees an account can claim in a token
+    pub fn get_fee_balance(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128(
+            self.fee_balances
+                .get(&account_id)
+                .and_then(|fees| fees.get(&token_id))
+                .copied()
+                .unwrap_or(0),
+        )
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Credit a fee to `account_id`. Fees are credited while settling fills,
+    /// so a new fee record is charged to the recipient's storage balance even
+    /// if it does not cover it.
+    pub(crate) fn internal_credit_fee(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: u128,
+    ) {
+        if amount == 0 {
+            return;
+        }
+
+        self.fee_balances.flush();
+        let initial_usage = env::storage_usage();
+        let balance = self
+            .fee_balances
+            .entry(account_id.clone())
+            .or_default()
+            .entry(token_id.clone())
+            .or_insert(0);
+        *balance = balance.checked_add(amount).expect("Balance overflow");
+
+        self.fee_balances.flush();
+        self.internal_charge_storage(account_id, initial_usage);
+    }
+}
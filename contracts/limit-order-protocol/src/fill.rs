@@ -0,0 +1,439 @@
+use near_sdk::{PromiseError, PromiseOrValue};
+
+use crate::math::{mul_div, Rounding};
+use crate::*;
+
+impl Order {
+    /// Maker and taker amounts of a fill at the current rate. `amount` is the
+    /// maker asset to buy if `by_making`, otherwise the taker asset to spend.
+    /// The maker amount is capped at what can be filled now, and both
+    /// amounts are rounded in the maker's favour: the maker amount down and
+    /// the taker amount up, so partial fills never pay below the order price.
+    pub fn fill_amounts(&self, amount: u128, by_making: bool) -> (u128, u128) {
+        let taking_amount = self.current_taking_amount();
+        let maker_amount = if by_making {
+            amount
+        } else {
+            mul_div(amount, self.making_amount, taking_amount, Rounding::Down)
+        }
+        .min(self.fillable_making_amount());
+        let taker_amount = mul_div(
+            maker_amount,
+            taking_amount,
+            self.making_amount,
+            Rounding::Up,
+        );
+        (maker_amount, taker_amount)
+    }
+
+    /// Check that the order can be filled now by `taker`, paying in
+    /// `token_id`. The taker is not checked when `None`.
+    pub fn check_fillable(
+        &self,
+        token_id: &AccountId,
+        taker: Option<&AccountId>,
+    ) -> Result<(), FillRejection> {
+        if self.status != OrderStatus::Open {
+            return Err(FillRejection::OrderNotOpen);
+        }
+        if self.is_expired() {
+            return Err(FillRejection::OrderExpired);
+        }
+        if &self.taker_asset != token_id {
+            return Err(FillRejection::WrongTakerAsset);
+        }
+        let is_allowed = |taker: &AccountId| {
+            self.allowed_taker
+                .as_ref()
+                .is_none_or(|allowed_taker| allowed_taker == taker)
+        };
+        if !taker.is_none_or(is_allowed) {
+            return Err(FillRejection::TakerNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Maker and taker amounts of a fill providing `amount` of the taker
+    /// asset, buying `making_amount` of the maker asset if set. The amounts
+    /// are checked against the amount provided, the fill's `threshold` and
+    /// the order's escrow.
+    pub fn checked_fill_amounts(
+        &self,
+        amount: u128,
+        making_amount: Option<u128>,
+        threshold: Option<u128>,
+    ) -> Result<(u128, u128), FillRejection> {
+        if self.fillable_making_amount() == 0 {
+            return Err(FillRejection::NothingFillable);
+        }
+        let (maker_amount, taker_amount) = match making_amount {
+            Some(making_amount) => self.fill_amounts(making_amount, true),
+            None => self.fill_amounts(amount, false),
+        };
+        if maker_amount == 0 {
+            return Err(FillRejection::AmountTooSmall);
+        }
+        if taker_amount > amount {
+            return Err(FillRejection::NotEnoughTakerAsset);
+        }
+        match (threshold, making_amount) {
+            (Some(threshold), Some(_)) if taker_amount > threshold => {
+                return Err(FillRejection::TakingAmountAboveThreshold);
+            }
+            (Some(threshold), None) if maker_amount < threshold => {
+                return Err(FillRejection::MakingAmountBelowThreshold);
+            }
+            _ => {}
+        }
+        if maker_amount > self.escrowed_amount {
+            return Err(FillRejection::NotFunded);
+        }
+        Ok((maker_amount, taker_amount))
+    }
+}
+
+impl FillRejection {
+    pub fn message(&self) -> &'static str {
+        match self {
+            FillRejection::OrderNotOpen => "Order is not open",
+            FillRejection::OrderExpired => "Order is expired",
+            FillRejection::WrongTakerAsset => "Token is not the order's taker asset",
+            FillRejection::TakerNotAllowed => "Taker is not allowed to fill this order",
+            FillRejection::NothingFillable => "Nothing of the order can be filled yet",
+            FillRejection::AmountTooSmall => "Fill amount is too small",
+            FillRejection::NotEnoughTakerAsset => "Not enough taker asset for this fill",
+            FillRejection::TakingAmountAboveThreshold => "Taking amount exceeds threshold",
+            FillRejection::MakingAmountBelowThreshold => "Making amount is below threshold",
+            FillRejection::NotFunded => "Order is not funded for this amount",
+            FillRejection::PredicateNotSatisfied => "Order predicate is not satisfied",
+            FillRejection::CannotUnwrap => "Only a wNEAR maker asset can be unwrapped",
+        }
+    }
+}
+
+#[near]
+impl LimitOrderProtocol {
+    /// Callback after the solver registry was asked whether the taker is a
+    /// registered `LimitOrder` solver
+    #[private]
+    pub fn on_fill_taker_checked(
+        &mut self,
+        request: FillRequest,
+        #[callback_result] is_solver: Result<bool, PromiseError>,
+    ) -> PromiseOrValue<U128> {
+        if !matches!(is_solver, Ok(true)) {
+            // Refund the taker
+            return PromiseOrValue::Value(request.amount);
+        }
+
+        self.internal_check_predicate(request)
+    }
+
+    /// Callback after the maker asset was sent to the taker, or unwrapped for
+    /// them. Settles the fill and runs the order's post interaction. Returns
+    /// the amount of the taker asset to refund from the original
+    /// `ft_transfer_call`.
+    #[private]
+    pub fn on_fill_maker_payout(
+        &mut self,
+        fill: PendingFill,
+        unused_amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> PromiseOrValue<U128> {
+        if call_result.is_err() {
+            // Undo the fill and refund everything the taker sent
+            self.internal_revert_fill(&fill);
+            return PromiseOrValue::Value(U128(fill.taker_amount.0 + unused_amount.0));
+        }
+
+        if fill.unwrap_near {
+            Promise::new(fill.taker.clone())
+                .transfer(NearToken::from_yoctonear(fill.maker_amount.0));
+        }
+        // Settling may remove the order, so keep it for the post interaction
+        let order = self
+            .orders
+            .get(&fill.order_id)
+            .expect("Order not found")
+            .clone();
+        let settlement = self.internal_settle_fill(fill);
+
+        self.internal_run_post_interaction(&order, settlement, unused_amount)
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Fill an order for a taker. Orders restricted to registered solvers are
+    /// settled once the solver registry confirmed the taker's permission, and
+    /// the order predicate is checked before settlement.
+    pub(crate) fn internal_fill_order(&mut self, request: FillRequest) -> PromiseOrValue<U128> {
+        let order = self.orders.get(&request.order_id).expect("Order not found");
+        self.assert_fillable(order, &request);
+        if !order.solvers_only {
+            return self.internal_check_predicate(request);
+        }
+        let settle_gas = settle_callback_gas(order, &request);
+        let predicate_gas = order
+            .predicate
+            .as_ref()
+            .map(|predicate| predicate.resolve_gas(settle_gas))
+            .unwrap_or(Gas::from_gas(0));
+
+        let solver_registry_id = self
+            .solver_registry_id
+            .clone()
+            .expect("Solver registry is not set");
+        ext_solver_registry::ext(solver_registry_id)
+            .with_static_gas(GAS_FOR_SOLVER_CHECK)
+            .has_solver_permission(request.taker.clone(), SolverType::LimitOrder)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(settle_gas.saturating_add(predicate_gas))
+                    .on_fill_taker_checked(request),
+            )
+            .into()
+    }
+
+    fn assert_fillable(&self, order: &Order, request: &FillRequest) {
+        for interaction in [
+            &order.pre_interaction,
+            &request.interaction,
+            &order.post_interaction,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            self.assert_interaction_allowed(interaction);
+        }
+        if let Some(predicate) = order.predicate.as_ref() {
+            self.assert_predicate_allowed(predicate);
+        }
+        if let Err(rejection) = order.check_fillable(&request.token_id, Some(&request.taker)) {
+            env::panic_str(rejection.message());
+        }
+    }
+
+    /// Fill an order with the taker asset received in `request`.
+    /// The order is updated up front so concurrent fills cannot spend the same
+    /// escrow, and `on_fill_maker_payout` reverts it if the payout fails.
+    pub(crate) fn internal_execute_fill(&mut self, request: FillRequest) -> PromiseOrValue<U128> {
+        let fill = self.internal_reserve_fill(&request);
+        let order = self.orders.get(&fill.order_id).expect("Order not found");
+        let unused_amount = U128(request.amount.0 - fill.taker_amount.0);
+
+        // Run the interactions before any tokens leave the protocol, so a
+        // failing interaction can still revert the fill. The post interaction
+        // runs once the fill is settled.
+        let interactions: Vec<Interaction> = [order.pre_interaction.clone(), request.interaction]
+            .into_iter()
+            .flatten()
+            .collect();
+        if interactions.is_empty() {
+            self.internal_payout_fill(fill, unused_amount).into()
+        } else {
+            self.internal_run_interactions(fill, unused_amount, interactions)
+        }
+    }
+
+    /// Take the part of the order filled by `request` out of it, priced at the
+    /// current auction rate. Anything above the remaining order size is left
+    /// unused.
+    pub(crate) fn internal_reserve_fill(&mut self, request: &FillRequest) -> PendingFill {
+        let mut order = self
+            .orders
+            .get(&request.order_id)
+            .expect("Order not found")
+            .clone();
+        self.assert_fillable(&order, request);
+
+        let (maker_amount, taker_amount) = order
+            .checked_fill_amounts(
+                request.amount.0,
+                request.making_amount.map(|amount| amount.0),
+                request.threshold.map(|threshold| threshold.0),
+            )
+            .unwrap_or_else(|rejection| env::panic_str(rejection.message()));
+        self.check_unwrap(&order, request.unwrap_near)
+            .unwrap_or_else(|rejection| env::panic_str(rejection.message()));
+
+        // Update order
+        order.filled_making_amount += maker_amount;
+        order.escrowed_amount -= maker_amount;
+        order.fills_in_flight += 1;
+
+        if order.remaining_making_amount() == 0 {
+            order.status = OrderStatus::Filled;
+        }
+
+        // Store updated order
+        self.orders.insert(request.order_id.clone(), order.clone());
+
+        let (protocol_fee, integrator_fee) = self.fill_fees(&order, taker_amount);
+        PendingFill {
+            order_id: request.order_id.clone(),
+            taker: request.taker.clone(),
+            maker_amount: U128(maker_amount),
+            taker_amount: U128(taker_amount),
+            protocol_fee: U128(protocol_fee),
+            integrator_fee: U128(integrator_fee),
+            unwrap_near: request.unwrap_near,
+        }
+    }
+
+    /// Check that the maker asset of `order` can be paid out as native NEAR,
+    /// if `unwrap_near` asks for it
+    pub(crate) fn check_unwrap(
+        &self,
+        order: &Order,
+        unwrap_near: bool,
+    ) -> Result<(), FillRejection> {
+        if unwrap_near && self.wnear_id.as_ref() != Some(&order.maker_asset) {
+            return Err(FillRejection::CannotUnwrap);
+        }
+        Ok(())
+    }
+
+    /// Protocol and integrator fees taken from `taker_amount` paid for `order`
+    pub(crate) fn fill_fees(&self, order: &Order, taker_amount: u128) -> (u128, u128) {
+        let protocol_fee = fee_amount(taker_amount, self.protocol_fee_bps);
+        let integrator_fee = order
+            .integrator_fee
+            .as_ref()
+            .map(|integrator_fee| fee_amount(taker_amount, integrator_fee.fee_bps))
+            .unwrap_or(0);
+        (protocol_fee, integrator_fee)
+    }
+
+    /// Transfer the maker asset to the taker, then settle or roll back the fill.
+    /// wNEAR paid out as native NEAR is unwrapped first and sent in the
+    /// callback.
+    pub(crate) fn internal_payout_fill(&self, fill: PendingFill, unused_amount: U128) -> Promise {
+        let order = self.orders.get(&fill.order_id).expect("Order not found");
+        let payout = if fill.unwrap_near {
+            ext_wnear::ext(order.maker_asset.clone())
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .with_static_gas(GAS_FOR_NEAR_WITHDRAW)
+                .near_withdraw(fill.maker_amount)
+        } else {
+            ext_ft::ext(order.maker_asset.clone())
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .with_static_gas(GAS_FOR_FT_TRANSFER)
+                .ft_transfer(
+                    fill.taker.clone(),
+                    fill.maker_amount,
+                    Some(format!("Fill order {}", fill.order_id)),
+                )
+        };
+        payout.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(
+                    GAS_FOR_FILL_CALLBACK
+                        .saturating_add(order.proceeds_gas())
+                        .saturating_add(order.post_interaction_gas()),
+                )
+                .on_fill_maker_payout(fill, unused_amount),
+        )
+    }
+
+    /// Pay out the taker asset of a fill whose maker asset reached the taker:
+    /// keep the fees and send the rest to the order's receiver. Returns the
+    /// transfer of the proceeds.
+    pub(crate) fn internal_settle_fill(&mut self, fill: PendingFill) -> Promise {
+        let PendingFill {
+            order_id,
+            taker,
+            maker_amount,
+            taker_amount,
+            protocol_fee,
+            integrator_fee,
+            ..
+        } = fill;
+        let mut order = self.orders.get(&order_id).expect("Order not found").clone();
+        order.fills_in_flight -= 1;
+        self.orders.insert(order_id.clone(), order.clone());
+
+        // Keep the fees and pay the maker the rest
+        let protocol_fee_recipient = self.protocol_fee_recipient.clone();
+        self.internal_credit_fee(&protocol_fee_recipient, &order.taker_asset, protocol_fee.0);
+        let integrator = order
+            .integrator_fee
+            .as_ref()
+            .map(|integrator_fee| integrator_fee.recipient.clone());
+        if let Some(integrator) = integrator.as_ref() {
+            self.internal_credit_fee(integrator, &order.taker_asset, integrator_fee.0);
+        }
+        let proceeds = self.internal_send_proceeds(
+            &order,
+            taker_amount.0 - protocol_fee.0 - integrator_fee.0,
+            format!("Fill order {}", order_id),
+        );
+
+        OrderFilled {
+            order_id: order_id.clone(),
+            maker: order.maker.clone(),
+            taker,
+            maker_asset: order.maker_asset.clone(),
+            taker_asset: order.taker_asset.clone(),
+            maker_amount: maker_amount.0,
+            taker_amount: taker_amount.0,
+            protocol_fee: protocol_fee.0,
+            integrator_fee: integrator_fee.0,
+            integrator,
+        }
+        .emit();
+
+        // Return rounding dust left in escrow once the order is complete, and
+        // remove orders closed while no more fills of them are in flight
+        if order.status == OrderStatus::Filled && order.fills_in_flight == 0 {
+            self.internal_finalize_order(&order_id);
+        }
+        self.internal_refund_order_storage(&order_id);
+
+        proceeds
+    }
+
+    /// Give back to the order what an unsettled fill took from it
+    pub(crate) fn internal_revert_fill(&mut self, fill: &PendingFill) {
+        let mut order = self
+            .orders
+            .get(&fill.order_id)
+            .expect("Order not found")
+            .clone();
+
+        order.filled_making_amount -= fill.maker_amount.0;
+        order.fills_in_flight -= 1;
+        match order.status {
+            OrderStatus::Open | OrderStatus::Filled => {
+                order.status = OrderStatus::Open;
+                order.escrowed_amount += fill.maker_amount.0;
+            }
+            // The order was closed meanwhile, so its escrow is already gone
+            OrderStatus::Cancelled | OrderStatus::Expired => {
+                self.internal_deposit_balance(
+                    &order.maker,
+                    &order.maker_asset,
+                    fill.maker_amount.0,
+                );
+            }
+        }
+        self.orders.insert(fill.order_id.clone(), order);
+        self.internal_refund_order_storage(&fill.order_id);
+    }
+}
+
+/// Gas for a callback that goes on to settle a fill of `order`, including the
+/// interactions of the fill
+pub(crate) fn settle_callback_gas(order: &Order, request: &FillRequest) -> Gas {
+    [&order.pre_interaction, &request.interaction]
+        .into_iter()
+        .flatten()
+        .fold(
+            GAS_FOR_FILL_CHECK_CALLBACK
+                .saturating_add(order.proceeds_gas())
+                .saturating_add(order.post_interaction_gas()),
+            |gas, interaction| {
+                gas.saturating_add(interaction.gas)
+                    .saturating_add(GAS_FOR_INTERACTION_CALLBACK)
+            },
+        )
+}
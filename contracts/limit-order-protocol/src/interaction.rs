@@ -0,0 +1,169 @@
+use near_sdk::{PromiseOrValue, PromiseResult};
+
+use crate::*;
+
+impl Interaction {
+    /// Call the interaction without a deposit, so it cannot pass the one
+    /// yoctoNEAR check of methods that move the protocol's assets
+    pub(crate) fn call(&self) -> Promise {
+        Promise::new(self.contract_id.clone()).function_call(
+            self.method_name.clone(),
+            self.args.clone().into_bytes(),
+            NearToken::from_yoctonear(0),
+            self.gas,
+        )
+    }
+}
+
+impl Order {
+    /// Gas the post interaction and its callback take once a fill is settled
+    pub fn post_interaction_gas(&self) -> Gas {
+        self.post_interaction
+            .as_ref()
+            .map(|interaction| interaction.gas.saturating_add(GAS_FOR_INTERACTION_CALLBACK))
+            .unwrap_or(Gas::from_gas(0))
+    }
+}
+
+/// Gas needed to run `interactions` and their callbacks, then pay out the
+/// fill of `order`
+pub(crate) fn interactions_gas(order: &Order, interactions: &[Interaction]) -> Gas {
+    interactions.iter().fold(
+        GAS_FOR_FT_TRANSFER
+            .saturating_add(GAS_FOR_FILL_CALLBACK)
+            .saturating_add(order.proceeds_gas())
+            .saturating_add(order.post_interaction_gas()),
+        |gas, interaction| {
+            gas.saturating_add(interaction.gas)
+                .saturating_add(GAS_FOR_INTERACTION_CALLBACK)
+        },
+    )
+}
+
+#[near]
+impl LimitOrderProtocol {
+    /// Callback after an interaction of a fill. Runs the next interaction, or
+    /// pays out the fill once all of them succeeded.
+    #[private]
+    pub fn on_fill_interaction(
+        &mut self,
+        fill: PendingFill,
+        unused_amount: U128,
+        interactions: Vec<Interaction>,
+    ) -> PromiseOrValue<U128> {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            // Undo the fill and refund everything the taker sent
+            self.internal_revert_fill(&fill);
+            return PromiseOrValue::Value(U128(fill.taker_amount.0 + unused_amount.0));
+        }
+
+        if interactions.is_empty() {
+            self.internal_payout_fill(fill, unused_amount).into()
+        } else {
+            self.internal_run_interactions(fill, unused_amount, interactions)
+        }
+    }
+
+    /// Callback after the post interaction of a settled fill. The fill stands
+    /// whatever the interaction did, so a failure is only reported. Returns
+    /// `unused_amount` to refund from the original `ft_transfer_call`.
+    #[private]
+    pub fn on_fill_post_interaction(
+        &mut self,
+        order_id: String,
+        maker: AccountId,
+        contract_id: AccountId,
+        unused_amount: U128,
+    ) -> U128 {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            PostInteractionFailed {
+                order_id,
+                maker,
+                contract_id,
+            }
+            .emit();
+        }
+        unused_amount
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Interactions are made with the protocol as predecessor, so they must
+    /// not reach the protocol itself or a token it holds, whose balance they
+    /// could move
+    pub(crate) fn is_interaction_allowed(&self, interaction: &Interaction) -> bool {
+        interaction.contract_id != env::current_account_id()
+            && self.wnear_id.as_ref() != Some(&interaction.contract_id)
+            && !self.custodied_tokens.contains(&interaction.contract_id)
+    }
+
+    pub(crate) fn assert_interaction_allowed(&self, interaction: &Interaction) {
+        assert!(
+            self.is_interaction_allowed(interaction),
+            "Interactions cannot call the protocol or a token it holds"
+        );
+    }
+
+    /// Call the first of `interactions` and continue the fill in its callback.
+    /// The fill is reverted if the interaction is no longer allowed, as the
+    /// protocol may have received its contract as a token since the fill
+    /// started.
+    pub(crate) fn internal_run_interactions(
+        &mut self,
+        fill: PendingFill,
+        unused_amount: U128,
+        mut interactions: Vec<Interaction>,
+    ) -> PromiseOrValue<U128> {
+        let interaction = interactions.remove(0);
+        if !self.is_interaction_allowed(&interaction) {
+            self.internal_revert_fill(&fill);
+            return PromiseOrValue::Value(U128(fill.taker_amount.0 + unused_amount.0));
+        }
+
+        let order = self.orders.get(&fill.order_id).expect("Order not found");
+        interaction
+            .call()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(interactions_gas(order, &interactions))
+                    .on_fill_interaction(fill, unused_amount, interactions),
+            )
+            .into()
+    }
+
+    /// Call the post interaction of `order` once `settlement`, the delivery of
+    /// a fill's proceeds, is done, and resolve to `unused_amount`
+    pub(crate) fn internal_run_post_interaction(
+        &self,
+        order: &Order,
+        settlement: Promise,
+        unused_amount: U128,
+    ) -> PromiseOrValue<U128> {
+        let Some(interaction) = order.post_interaction.as_ref() else {
+            return PromiseOrValue::Value(unused_amount);
+        };
+        if !self.is_interaction_allowed(interaction) {
+            PostInteractionFailed {
+                order_id: order.id.clone(),
+                maker: order.maker.clone(),
+                contract_id: interaction.contract_id.clone(),
+            }
+            .emit();
+            return PromiseOrValue::Value(unused_amount);
+        }
+
+        settlement
+            .then(interaction.call())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_INTERACTION_CALLBACK)
+                    .on_fill_post_interaction(
+                        order.id.clone(),
+                        order.maker.clone(),
+                        interaction.contract_id.clone(),
+                        unused_amount,
+                    ),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,137 @@
+use crate::*;
+
+/// Nonces covered by a single bitmap slot
+const NONCES_PER_SLOT: u64 = 128;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Invalidate signed order nonces of the caller. Orders that were already
+    /// created from these nonces are cancelled and their escrow refunded. New
+    /// bitmap records are paid from the caller's storage balance.
+    pub fn cancel_orders(&mut self, nonces: Vec<u64>) {
+        let maker = env::predecessor_account_id();
+        for nonce in nonces.iter() {
+            self.internal_invalidate_nonce(&maker, *nonce);
+        }
+        self.assert_storage_paid(&maker);
+
+        let order_ids = self.internal_open_order_ids(&maker, |order| {
+            order.nonce.is_some_and(|nonce| nonces.contains(&nonce))
+        });
+        for order_id in order_ids.iter() {
+            self.internal_cancel_order(order_id);
+        }
+
+        NoncesInvalidated { maker, nonces }.emit();
+    }
+
+    /// Move the caller's `series` to the next epoch, cancelling every order
+    /// of the series. Returns the new epoch. A new epoch record is paid from
+    /// the caller's storage balance.
+    pub fn increase_epoch(&mut self, series: u64) -> u64 {
+        let maker = env::predecessor_account_id();
+        let epoch = self.internal_epoch_of(&maker, series) + 1;
+        self.epochs.flush();
+        let initial_usage = env::storage_usage();
+        self.epochs.insert((maker.clone(), series), epoch);
+        self.epochs.flush();
+        self.internal_charge_storage(&maker, initial_usage);
+        self.assert_storage_paid(&maker);
+
+        let order_ids = self.internal_open_order_ids(&maker, |order| order.series == series);
+        for order_id in order_ids.iter() {
+            self.internal_cancel_order(order_id);
+        }
+
+        EpochIncreased {
+            maker,
+            series,
+            epoch,
+        }
+        .emit();
+
+        epoch
+    }
+
+    /// Check whether a nonce of a maker can no longer be used
+    pub fn is_nonce_invalidated(&self, maker: AccountId, nonce: u64) -> bool {
+        self.internal_is_nonce_invalidated(&maker, nonce)
+    }
+
+    /// Get the invalidation bitmap of a maker for nonces
+    /// `slot * 128 .. slot * 128 + 127`, lowest bit first
+    pub fn get_nonce_bitmap(&self, maker: AccountId, slot: u64) -> U128 {
+        U128(self.internal_nonce_bitmap(&maker, slot))
+    }
+
+    /// Get the current epoch of a maker's order series
+    pub fn get_epoch(&self, maker: AccountId, series: u64) -> u64 {
+        self.internal_epoch_of(&maker, series)
+    }
+
+    /// Check whether a signed order can still be filled
+    pub fn is_order_valid(&self, order: OrderData) -> bool {
+        if let Some(stored) = self.orders.get(&hex::encode(order.hash())) {
+            return stored.status == OrderStatus::Open && !stored.is_expired();
+        }
+
+        order.expiration > env::block_timestamp()
+            && !self.internal_is_nonce_invalidated(&order.maker, order.nonce)
+            && order.epoch == self.internal_epoch_of(&order.maker, order.series)
+    }
+}
+
+impl LimitOrderProtocol {
+    fn internal_nonce_bitmap(&self, maker: &AccountId, slot: u64) -> u128 {
+        self.nonce_bitmaps
+            .get(&(maker.clone(), slot))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn internal_is_nonce_invalidated(&self, maker: &AccountId, nonce: u64) -> bool {
+        let bitmap = self.internal_nonce_bitmap(maker, nonce / NONCES_PER_SLOT);
+        bitmap & (1 << (nonce % NONCES_PER_SLOT)) != 0
+    }
+
+    /// Set the nonce's bit, charging a new bitmap record to the maker
+    pub(crate) fn internal_invalidate_nonce(&mut self, maker: &AccountId, nonce: u64) {
+        self.nonce_bitmaps.flush();
+        let initial_usage = env::storage_usage();
+        let bitmap = self
+            .nonce_bitmaps
+            .entry((maker.clone(), nonce / NONCES_PER_SLOT))
+            .or_insert(0);
+        *bitmap |= 1 << (nonce % NONCES_PER_SLOT);
+
+        self.nonce_bitmaps.flush();
+        self.internal_charge_storage(maker, initial_usage);
+    }
+
+    pub(crate) fn internal_epoch_of(&self, maker: &AccountId, series: u64) -> u64 {
+        self.epochs
+            .get(&(maker.clone(), series))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn internal_open_order_ids<F>(&self, maker: &AccountId, filter: F) -> Vec<String>
+    where
+        F: Fn(&Order) -> bool,
+    {
+        self.orders_by_maker
+            .get(maker)
+            .map(|order_ids| {
+                order_ids
+                    .iter()
+                    .filter(|id| {
+                        self.orders
+                            .get(*id)
+                            .is_some_and(|order| order.status == OrderStatus::Open && filter(order))
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
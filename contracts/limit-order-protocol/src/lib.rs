@@ -1,172 +1,164 @@
 use near_sdk::{
     borsh::{BorshDeserialize, BorshSerialize},
-    env, near, AccountId, Gas, NearToken, PanicOnDefault, Promise, Timestamp,
+    env,
+    json_types::U128,
+    near,
+    store::{IterableMap, IterableSet, LookupMap, LookupSet},
+    AccountId, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue, PublicKey, Timestamp,
 };
+use std::collections::HashMap;
 
+mod admin;
+mod auction;
+mod balances;
+mod batch;
 mod events;
 mod ext;
+mod fees;
+mod fill;
+mod interaction;
+mod invalidator;
+mod math;
+mod native;
 mod order;
+mod order_kind;
+mod predicate;
+mod signed_order;
+mod storage;
+mod sweep;
+mod token_receiver;
 mod types;
+mod upgrade;
+mod view;
 
 use events::*;
 use ext::*;
+use fees::{fee_amount, MAX_FEE_BPS};
+use fill::settle_callback_gas;
 use types::*;
+use view::book_order_info;
 
 // Gas constants
 const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_TRANSFER_CALL: Gas = Gas::from_tgas(50);
 const GAS_FOR_FT_TRANSFER_CALLBACK: Gas = Gas::from_tgas(10);
+const GAS_FOR_FILL_CALLBACK: Gas = Gas::from_tgas(40);
+const GAS_FOR_SOLVER_CHECK: Gas = Gas::from_tgas(10);
+const GAS_FOR_FILL_CHECK_CALLBACK: Gas = Gas::from_tgas(80);
+const GAS_FOR_PREDICATE_VIEW: Gas = Gas::from_tgas(10);
+const GAS_FOR_INTERACTION_CALLBACK: Gas = Gas::from_tgas(10);
+const GAS_FOR_NEAR_DEPOSIT: Gas = Gas::from_tgas(10);
+const GAS_FOR_NEAR_WITHDRAW: Gas = Gas::from_tgas(10);
+const GAS_FOR_NEAR_WRAPPED_CALLBACK: Gas = Gas::from_tgas(30);
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
 pub struct LimitOrderProtocol {
     pub owner_id: AccountId,
-    pub orders: std::collections::HashMap<String, Order>,
-    pub orders_by_maker: std::collections::HashMap<AccountId, Vec<String>>,
+    pub orders: LookupMap<String, Order>,
+    pub orders_by_maker: LookupMap<AccountId, IterableSet<String>>,
+    /// Every stored order id of each pair
+    pub orders_by_pair: LookupMap<Pair, IterableSet<String>>,
+    /// Order ids of each pair that have not been filled, cancelled or expired yet
+    pub active_orders_by_pair: IterableMap<Pair, IterableSet<String>>,
     pub next_order_id: u64,
+    /// Unlocked maker funds held by the protocol, keyed by account and token
+    pub balances: LookupMap<AccountId, HashMap<AccountId, u128>>,
+    /// ed25519 keys makers sign off-chain orders with
+    pub signing_keys: LookupMap<AccountId, Vec<PublicKey>>,
+    /// Bitmaps of signed order nonces that can no longer be filled, keyed by
+    /// maker and `nonce / 128`
+    pub nonce_bitmaps: LookupMap<(AccountId, u64), u128>,
+    /// Current epoch of each maker's order series, keyed by maker and series
+    pub epochs: LookupMap<(AccountId, u64), u64>,
+    /// Solver registry checked for orders restricted to registered solvers
+    pub solver_registry_id: Option<AccountId>,
+    /// Protocol fee in basis points of the taker amount
+    pub protocol_fee_bps: u32,
+    pub protocol_fee_recipient: AccountId,
+    /// Claimable fees, keyed by recipient and token
+    pub fee_balances: LookupMap<AccountId, HashMap<AccountId, u128>>,
+    /// Ids of every open order, walked by `sweep_expired`
+    pub open_orders: IterableSet<String>,
+    /// Position in `open_orders` the next sweep starts at
+    pub sweep_cursor: u32,
+    /// NEP-145 storage balances that pay for the orders of each maker
+    pub storage_balances: LookupMap<AccountId, AccountStorage>,
+    /// wNEAR contract native NEAR is wrapped with
+    pub wnear_id: Option<AccountId>,
+    /// Every token the protocol has received, which interactions cannot call
+    pub custodied_tokens: LookupSet<AccountId>,
+    /// Contracts predicates can make view calls to
+    pub oracles: IterableSet<AccountId>,
 }
 
 #[near]
 impl LimitOrderProtocol {
     #[init]
     pub fn new(owner_id: AccountId) -> Self {
+        upgrade::write_state_version();
         Self {
-            owner_id,
-            orders: std::collections::HashMap::new(),
-            orders_by_maker: std::collections::HashMap::new(),
+            owner_id: owner_id.clone(),
+            orders: LookupMap::new(Prefix::Orders),
+            orders_by_maker: LookupMap::new(Prefix::OrdersByMaker),
+            orders_by_pair: LookupMap::new(Prefix::OrdersByPair),
+            active_orders_by_pair: IterableMap::new(Prefix::ActiveOrdersByPair),
             next_order_id: 1,
+            balances: LookupMap::new(Prefix::Balances),
+            signing_keys: LookupMap::new(Prefix::SigningKeys),
+            nonce_bitmaps: LookupMap::new(Prefix::NonceBitmaps),
+            epochs: LookupMap::new(Prefix::Epochs),
+            solver_registry_id: None,
+            protocol_fee_bps: 0,
+            protocol_fee_recipient: owner_id,
+            fee_balances: LookupMap::new(Prefix::FeeBalances),
+            open_orders: IterableSet::new(Prefix::OpenOrders),
+            sweep_cursor: 0,
+            storage_balances: LookupMap::new(Prefix::StorageBalances),
+            wnear_id: None,
+            custodied_tokens: LookupSet::new(Prefix::CustodiedTokens),
+            oracles: IterableSet::new(Prefix::Oracles),
         }
     }
 
-    /// Create a new limit order
-    pub fn create_order(
-        &mut self,
-        maker_asset: AccountId,
-        taker_asset: AccountId,
-        making_amount: u128,
-        taking_amount: u128,
-        maker: AccountId,
-        expiration: Timestamp,
-    ) -> String {
-        // Validate inputs
-        assert!(making_amount > 0, "Making amount must be greater than 0");
-        assert!(taking_amount > 0, "Taking amount must be greater than 0");
-        assert!(expiration > env::block_timestamp(), "Order must not be expired");
-        assert!(maker == env::predecessor_account_id(), "Only maker can create order");
-
-        // Generate order ID
-        let order_id = format!("order_{}", self.next_order_id);
-        self.next_order_id += 1;
-
-        // Create order
-        let order = Order {
-            id: order_id.clone(),
-            maker_asset,
-            taker_asset,
-            making_amount,
-            taking_amount,
-            maker: maker.clone(),
-            expiration,
-            status: OrderStatus::Open,
-            created_at: env::block_timestamp(),
+    /// Create a new limit order. With `native`, the order sells wNEAR and is
+    /// funded from the attached deposit, which is wrapped for it. The rest of
+    /// the deposit is added to the maker's storage balance, which pays for
+    /// storing the order.
+    #[payable]
+    pub fn create_order(&mut self, order: OrderData, native: Option<bool>) -> PromiseOrValue<String> {
+        assert!(order.maker == env::predecessor_account_id(), "Only maker can create order");
+
+        let native_amount = if native.unwrap_or(false) {
+            assert!(
+                self.wnear_id.as_ref() == Some(&order.maker_asset),
+                "Native NEAR orders must sell wNEAR"
+            );
+            order.making_amount.0
+        } else {
+            0
         };
-
-        // Store order
-        self.orders.insert(order_id.clone(), order.clone());
-
-        // Add to maker's orders
-        self.orders_by_maker
-            .entry(maker.clone())
-            .or_insert_with(Vec::new)
-            .push(order_id.clone());
-
-        // Emit event
-        OrderCreated {
-            order_id: order_id.clone(),
-            maker,
-            maker_asset: order.maker_asset,
-            taker_asset: order.taker_asset,
-            making_amount: order.making_amount,
-            taking_amount: order.taking_amount,
-        }
-        .emit();
-
-        order_id
-    }
-
-    /// Fill a limit order
-    pub fn fill_order(
-        &mut self,
-        order_id: String,
-        taker_amount: u128,
-    ) -> Promise {
-        // Get order
-        let mut order = self
-            .orders
-            .get(&order_id)
-            .expect("Order not found")
-            .clone();
-
-        // Validate order
-        assert!(order.status == OrderStatus::Open, "Order is not open");
-        assert!(env::block_timestamp() < order.expiration, "Order is expired");
-        assert!(taker_amount <= order.taking_amount, "Amount exceeds order size");
-
-        // Calculate maker amount
-        let maker_amount = (order.making_amount * taker_amount) / order.taking_amount;
-
-        // Update order
-        order.taking_amount -= taker_amount;
-        order.making_amount -= maker_amount;
-
-        if order.taking_amount == 0 {
-            order.status = OrderStatus::Filled;
+        let deposit = env::attached_deposit()
+            .as_yoctonear()
+            .checked_sub(native_amount)
+            .expect("Attached deposit does not cover the making amount");
+        if deposit > 0 {
+            self.internal_storage_deposit(&order.maker, deposit);
         }
 
-        // Store updated order
-        self.orders.insert(order_id.clone(), order.clone());
-
-        // Transfer tokens
-        let taker = env::predecessor_account_id();
-
-        // Transfer maker asset from maker to taker
-        let transfer_maker_promise = ext_ft::ext(order.maker_asset.clone())
-            .with_attached_deposit(NearToken::from_yoctonear(1))
-            .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(
-                taker.clone(),
-                maker_amount,
-                Some(format!("Fill order {}", order_id)),
-            );
-
-        // Transfer taker asset from taker to maker
-        let transfer_taker_promise = ext_ft::ext(order.taker_asset.clone())
-            .with_attached_deposit(NearToken::from_yoctonear(1))
-            .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(
-                order.maker.clone(),
-                taker_amount,
-                Some(format!("Fill order {}", order_id)),
-            );
-
-        // Emit event
-        OrderFilled {
-            order_id: order_id.clone(),
-            maker: order.maker,
-            taker,
-            maker_asset: order.maker_asset,
-            taker_asset: order.taker_asset,
-            maker_amount,
-            taker_amount,
+        // Maker funds are added later through `ft_transfer_call` with a `fund_order` message
+        let maker = order.maker.clone();
+        let order_id = self.internal_next_order_id();
+        let order_id = self.internal_create_order(order_id, order, None);
+        if native_amount == 0 {
+            return PromiseOrValue::Value(order_id);
         }
-        .emit();
-
-        // Return promises
-        transfer_maker_promise.then(transfer_taker_promise)
+        self.internal_wrap_near(order_id, maker, native_amount).into()
     }
 
     /// Cancel an order
     pub fn cancel_order(&mut self, order_id: String) {
-        let mut order = self
+        let order = self
             .orders
             .get(&order_id)
             .expect("Order not found")
@@ -176,38 +168,43 @@ impl LimitOrderProtocol {
         assert!(order.maker == env::predecessor_account_id(), "Only maker can cancel");
         assert!(order.status == OrderStatus::Open, "Order is not open");
 
-        // Update order
-        order.status = OrderStatus::Cancelled;
-
-        // Store updated order
-        self.orders.insert(order_id.clone(), order.clone());
+        self.internal_cancel_order(&order_id);
+    }
 
-        // Remove from maker's orders
-        if let Some(maker_orders) = self.orders_by_maker.get_mut(&order.maker) {
-            maker_orders.retain(|id| id != &order_id);
-        }
+    /// Reprice an open order or move its expiration, keeping its ID, its
+    /// place in the book and what was filled so far. Amounts are the order's
+    /// total terms, filled part included. Lowering the making amount refunds
+    /// escrow above the new remaining amount, raising it leaves the difference
+    /// to be funded with a `fund_order` message.
+    pub fn amend_order(
+        &mut self,
+        order_id: String,
+        new_taking_amount: U128,
+        new_expiration: Timestamp,
+        new_making_amount: Option<U128>,
+    ) {
+        let order = self.orders.get(&order_id).expect("Order not found");
+        assert!(order.maker == env::predecessor_account_id(), "Only maker can amend");
+
+        self.internal_amend_order(
+            &order_id,
+            new_making_amount.map(|amount| amount.0),
+            new_taking_amount.0,
+            new_expiration,
+        );
+    }
 
-        // Emit event
-        OrderCancelled {
-            order_id: order_id.clone(),
-            maker: order.maker,
-        }
-        .emit();
+    /// Mark an expired order as `Expired` and refund its unfilled maker funds
+    /// and storage deposit. Can be called by anyone once the order has passed
+    /// its expiration.
+    pub fn refund_expired_order(&mut self, order_id: String) {
+        self.internal_expire_order(&order_id);
+        self.internal_refund_order_storage(&order_id);
     }
 
-    /// Get order by ID
+    /// Get order by ID. Iceberg orders only show their clip.
     pub fn get_order(&self, order_id: String) -> Option<OrderInfo> {
-        self.orders.get(&order_id).map(|order| OrderInfo {
-            id: order.id.clone(),
-            maker_asset: order.maker_asset.clone(),
-            taker_asset: order.taker_asset.clone(),
-            making_amount: order.making_amount,
-            taking_amount: order.taking_amount,
-            maker: order.maker.clone(),
-            expiration: order.expiration,
-            status: order.status.clone(),
-            created_at: order.created_at,
-        })
+        self.orders.get(&order_id).map(book_order_info)
     }
 
     /// Get orders by maker
@@ -223,12 +220,9 @@ impl LimitOrderProtocol {
             .unwrap_or_default()
     }
 
-    /// Generate a hash for order verification
-    pub fn hash_order_data(&self, data: String) -> String {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(data.as_bytes());
-        format!("{:x}", hasher.finalize())
+    /// Get the hash makers sign for an off-chain order, hex encoded
+    pub fn hash_order(&self, order: OrderData) -> String {
+        hex::encode(order.hash())
     }
 
     /// Generate a random order ID
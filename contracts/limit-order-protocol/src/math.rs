@@ -0,0 +1,107 @@
+use std::cmp::Ordering;
+
+#[allow(clippy::all)]
+mod u256 {
+    uint::construct_uint! {
+        /// 256-bit unsigned integer for intermediate amount arithmetic
+        pub struct U256(4);
+    }
+}
+
+pub use u256::U256;
+
+/// Direction an inexact division is rounded in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// `a * b / c` computed in 256 bits and rounded as requested. Panics if the
+/// result does not fit in 128 bits.
+pub fn mul_div(a: u128, b: u128, c: u128, rounding: Rounding) -> u128 {
+    assert!(c > 0, "Division by zero");
+    let product = U256::from(a) * U256::from(b);
+    let divisor = U256::from(c);
+    let mut result = product / divisor;
+    if rounding == Rounding::Up && !(product % divisor).is_zero() {
+        result += U256::one();
+    }
+    assert!(result.bits() <= 128, "Amount overflow");
+    result.low_u128()
+}
+
+/// Compare the prices `taking_a / making_a` and `taking_b / making_b` exactly,
+/// by cross multiplying in 256 bits
+pub fn cmp_price(taking_a: u128, making_a: u128, taking_b: u128, making_b: u128) -> Ordering {
+    (U256::from(taking_a) * U256::from(making_b))
+        .cmp(&(U256::from(taking_b) * U256::from(making_a)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div_exact() {
+        assert_eq!(mul_div(6, 4, 3, Rounding::Down), 8);
+        assert_eq!(mul_div(6, 4, 3, Rounding::Up), 8);
+        assert_eq!(mul_div(0, 4, 3, Rounding::Up), 0);
+    }
+
+    #[test]
+    fn test_mul_div_rounds_down() {
+        assert_eq!(mul_div(7, 1, 2, Rounding::Down), 3);
+        assert_eq!(mul_div(1, 1, 3, Rounding::Down), 0);
+        assert_eq!(mul_div(2, 2, 3, Rounding::Down), 1);
+    }
+
+    #[test]
+    fn test_mul_div_rounds_up() {
+        assert_eq!(mul_div(7, 1, 2, Rounding::Up), 4);
+        assert_eq!(mul_div(1, 1, 3, Rounding::Up), 1);
+        assert_eq!(mul_div(2, 2, 3, Rounding::Up), 2);
+    }
+
+    #[test]
+    fn test_mul_div_large_product() {
+        // The product overflows 128 bits, the result does not
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down),
+            u128::MAX
+        );
+        assert_eq!(mul_div(u128::MAX, 3, 6, Rounding::Down), u128::MAX / 2);
+        assert_eq!(mul_div(u128::MAX, 3, 6, Rounding::Up), u128::MAX / 2 + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount overflow")]
+    fn test_mul_div_overflow() {
+        mul_div(u128::MAX, 2, 1, Rounding::Down);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount overflow")]
+    fn test_mul_div_round_up_overflow() {
+        mul_div(u128::MAX, 3, 2, Rounding::Up);
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn test_mul_div_by_zero() {
+        mul_div(1, 1, 0, Rounding::Down);
+    }
+
+    #[test]
+    fn test_cmp_price() {
+        assert_eq!(cmp_price(2, 1, 4, 2), Ordering::Equal);
+        assert_eq!(cmp_price(3, 1, 4, 2), Ordering::Greater);
+        assert_eq!(cmp_price(1, 1, 4, 2), Ordering::Less);
+        // Prices that differ by less than a unit after integer division
+        assert_eq!(cmp_price(7, 3, 5, 2), Ordering::Less);
+        assert_eq!(
+            cmp_price(u128::MAX, u128::MAX - 1, u128::MAX - 1, u128::MAX - 2),
+            Ordering::Less
+        );
+    }
+}
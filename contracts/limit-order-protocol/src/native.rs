@@ -0,0 +1,68 @@
+use near_sdk::PromiseError;
+
+use crate::*;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Callback after the NEAR attached to `create_order` was wrapped for the
+    /// order's escrow. If wrapping failed the order is cancelled and the NEAR
+    /// returned to the maker.
+    #[private]
+    pub fn on_near_wrapped(
+        &mut self,
+        order_id: String,
+        maker: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> String {
+        let is_open = self
+            .orders
+            .get(&order_id)
+            .is_some_and(|order| order.status == OrderStatus::Open && !order.is_expired());
+
+        if call_result.is_err() {
+            if is_open {
+                self.internal_cancel_order(&order_id);
+            }
+            Promise::new(maker).transfer(NearToken::from_yoctonear(amount.0));
+            return order_id;
+        }
+
+        self.custodied_tokens.insert(token_id.clone());
+
+        // Keep what the order cannot take, e.g. once it was cancelled meanwhile,
+        // in the maker's balance
+        let used = if is_open {
+            self.internal_fund_order(&order_id, &token_id, &maker, amount.0)
+        } else {
+            0
+        };
+        if amount.0 > used {
+            self.internal_deposit_balance(&maker, &token_id, amount.0 - used);
+        }
+
+        order_id
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Wrap `amount` of NEAR and escrow it for the order
+    pub(crate) fn internal_wrap_near(
+        &self,
+        order_id: String,
+        maker: AccountId,
+        amount: u128,
+    ) -> Promise {
+        let wnear_id = self.wnear_id.clone().expect("wNEAR is not set");
+        ext_wnear::ext(wnear_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(amount))
+            .with_static_gas(GAS_FOR_NEAR_DEPOSIT)
+            .near_deposit()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_NEAR_WRAPPED_CALLBACK)
+                    .on_near_wrapped(order_id, maker, wnear_id, U128(amount)),
+            )
+    }
+}
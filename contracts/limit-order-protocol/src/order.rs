@@ -1 +1,404 @@
-pub use crate::types::*; 
\ No newline at end of file
+use crate::storage::storage_cost_since;
+use crate::*;
+
+impl LimitOrderProtocol {
+    pub(crate) fn internal_next_order_id(&mut self) -> String {
+        let order_id = format!("order_{}", self.next_order_id);
+        self.next_order_id += 1;
+        order_id
+    }
+
+    /// Validate and store a new order. `nonce` is set for orders created from
+    /// a maker signature.
+    pub(crate) fn internal_create_order(
+        &mut self,
+        order_id: String,
+        data: OrderData,
+        nonce: Option<u64>,
+    ) -> String {
+        // Validate inputs
+        assert!(
+            data.making_amount.0 > 0,
+            "Making amount must be greater than 0"
+        );
+        assert!(
+            data.taking_amount.0 > 0,
+            "Taking amount must be greater than 0"
+        );
+        assert!(
+            data.expiration > env::block_timestamp(),
+            "Order must not be expired"
+        );
+        assert!(
+            data.maker_asset != data.taker_asset,
+            "Maker and taker assets must be different"
+        );
+        assert!(!self.orders.contains_key(&order_id), "Order already exists");
+        if let Some(auction) = data.auction.as_ref() {
+            auction.assert_valid();
+        }
+        data.kind.assert_valid();
+        if let Some(predicate) = data.predicate.as_ref() {
+            self.assert_predicate_allowed(predicate);
+        }
+        for interaction in [&data.pre_interaction, &data.post_interaction]
+            .into_iter()
+            .flatten()
+        {
+            self.assert_interaction_allowed(interaction);
+        }
+        if let Some(integrator_fee) = data.integrator_fee.as_ref() {
+            assert!(
+                integrator_fee.fee_bps <= MAX_FEE_BPS,
+                "Integrator fee is too high"
+            );
+        }
+        assert!(
+            data.epoch == self.internal_epoch_of(&data.maker, data.series),
+            "Order epoch is outdated"
+        );
+
+        // Create order
+        let mut order = Order {
+            id: order_id.clone(),
+            maker_asset: data.maker_asset,
+            taker_asset: data.taker_asset,
+            making_amount: data.making_amount.0,
+            taking_amount: data.taking_amount.0,
+            filled_making_amount: 0,
+            maker: data.maker.clone(),
+            expiration: data.expiration,
+            status: OrderStatus::Open,
+            created_at: env::block_timestamp(),
+            escrowed_amount: 0,
+            storage_deposit: 0,
+            fills_in_flight: 0,
+            nonce,
+            series: data.series,
+            epoch: data.epoch,
+            auction: data.auction,
+            allowed_taker: data.allowed_taker,
+            solvers_only: data.solvers_only,
+            predicate: data.predicate,
+            pre_interaction: data.pre_interaction,
+            post_interaction: data.post_interaction,
+            integrator_fee: data.integrator_fee,
+            kind: data.kind,
+            receiver: data.receiver,
+        };
+
+        // Store order
+        self.internal_flush_order_book(&order);
+        let initial_usage = env::storage_usage();
+        self.orders.insert(order_id.clone(), order.clone());
+
+        // Add to maker's orders
+        self.internal_maker_orders_mut(&data.maker)
+            .insert(order_id.clone());
+
+        // Add to the pair's indexes
+        let pair = order.pair();
+        self.internal_pair_orders_mut(&pair)
+            .insert(order_id.clone());
+        self.internal_active_pair_orders_mut(&pair)
+            .insert(order_id.clone());
+        self.open_orders.insert(order_id.clone());
+
+        // Charge the storage the order took to the maker's storage balance
+        self.internal_flush_order_book(&order);
+        order.storage_deposit = storage_cost_since(initial_usage);
+        self.internal_lock_storage(&order.maker, order.storage_deposit);
+        self.orders.insert(order_id.clone(), order.clone());
+
+        // Emit event, with only the clip of iceberg orders
+        let info = book_order_info(&order);
+        OrderCreated {
+            order_id: order_id.clone(),
+            maker: data.maker,
+            maker_asset: order.maker_asset,
+            taker_asset: order.taker_asset,
+            making_amount: info.making_amount,
+            taking_amount: info.taking_amount,
+        }
+        .emit();
+
+        order_id
+    }
+
+    /// Move `amount` of `token_id` sent by `sender_id` into the order's escrow.
+    /// Returns the amount used, which is capped at the order's unfunded amount.
+    pub(crate) fn internal_fund_order(
+        &mut self,
+        order_id: &String,
+        token_id: &AccountId,
+        sender_id: &AccountId,
+        amount: u128,
+    ) -> u128 {
+        let mut order = self.orders.get(order_id).expect("Order not found").clone();
+
+        assert!(order.status == OrderStatus::Open, "Order is not open");
+        assert!(!order.is_expired(), "Order is expired");
+        assert!(&order.maker == sender_id, "Only maker can fund order");
+        assert!(
+            &order.maker_asset == token_id,
+            "Token is not the order's maker asset"
+        );
+
+        let used = amount.min(order.unfunded_amount());
+        assert!(used > 0, "Order is already fully funded");
+
+        order.escrowed_amount += used;
+        self.orders.insert(order_id.clone(), order.clone());
+
+        OrderFunded {
+            order_id: order_id.clone(),
+            maker: order.maker,
+            amount: used,
+            escrowed_amount: order.escrowed_amount,
+        }
+        .emit();
+
+        used
+    }
+
+    /// Cancel an open order and refund its escrow and storage deposit.
+    /// Returns the refunded amount.
+    pub(crate) fn internal_cancel_order(&mut self, order_id: &String) -> u128 {
+        let mut order = self.orders.get(order_id).expect("Order not found").clone();
+
+        // Update order
+        order.status = OrderStatus::Cancelled;
+
+        // Store updated order
+        self.orders.insert(order_id.clone(), order.clone());
+
+        // Return unfilled maker funds
+        let refunded_amount = self.internal_finalize_order(order_id);
+
+        // Emit event
+        OrderCancelled {
+            order_id: order_id.clone(),
+            maker: order.maker,
+            refunded_amount,
+        }
+        .emit();
+
+        self.internal_refund_order_storage(order_id);
+
+        refunded_amount
+    }
+
+    /// Change the terms of an open order and refund escrow the new making
+    /// amount no longer needs. Returns the refunded amount.
+    pub(crate) fn internal_amend_order(
+        &mut self,
+        order_id: &String,
+        making_amount: Option<u128>,
+        taking_amount: u128,
+        expiration: Timestamp,
+    ) -> u128 {
+        let mut order = self.orders.get(order_id).expect("Order not found").clone();
+        let making_amount = making_amount.unwrap_or(order.making_amount);
+
+        assert!(order.status == OrderStatus::Open, "Order is not open");
+        assert!(!order.is_expired(), "Order is expired");
+        assert!(
+            making_amount > order.filled_making_amount,
+            "Making amount must exceed the filled amount"
+        );
+        assert!(taking_amount > 0, "Taking amount must be greater than 0");
+        assert!(
+            expiration > env::block_timestamp(),
+            "Order must not be expired"
+        );
+
+        order.making_amount = making_amount;
+        order.taking_amount = taking_amount;
+        order.expiration = expiration;
+
+        // Escrow above the new remaining amount goes back to the maker
+        let refunded_amount = order
+            .escrowed_amount
+            .saturating_sub(order.remaining_making_amount());
+        order.escrowed_amount -= refunded_amount;
+        self.orders.insert(order_id.clone(), order.clone());
+        if refunded_amount > 0 {
+            self.internal_send(
+                &order.maker,
+                &order.maker_asset,
+                refunded_amount,
+                format!("Refund order {}", order_id),
+            );
+        }
+
+        // Emit event, with only the clip of iceberg orders
+        let info = book_order_info(&order);
+        OrderAmended {
+            order_id: order_id.clone(),
+            maker: order.maker,
+            making_amount: info.making_amount,
+            taking_amount: info.taking_amount,
+            expiration,
+            refunded_amount,
+        }
+        .emit();
+
+        refunded_amount
+    }
+
+    /// Expire an open order past its expiration and refund its escrow.
+    /// Returns the refunded amount.
+    pub(crate) fn internal_expire_order(&mut self, order_id: &String) -> u128 {
+        let mut order = self.orders.get(order_id).expect("Order not found").clone();
+
+        assert!(order.status == OrderStatus::Open, "Order is not open");
+        assert!(order.is_expired(), "Order is not expired");
+
+        order.status = OrderStatus::Expired;
+        self.orders.insert(order_id.clone(), order.clone());
+
+        let refunded_amount = self.internal_finalize_order(order_id);
+
+        OrderExpired {
+            order_id: order_id.clone(),
+            maker: order.maker,
+            refunded_amount,
+        }
+        .emit();
+
+        refunded_amount
+    }
+
+    /// Clean up after an order left the `Open` state: drop it from the active
+    /// pair and open order indexes, return its remaining escrow to the maker and burn the nonce
+    /// of signed orders so they cannot be submitted again. Returns the
+    /// refunded amount.
+    pub(crate) fn internal_finalize_order(&mut self, order_id: &String) -> u128 {
+        let order = self.orders.get(order_id).expect("Order not found").clone();
+
+        let pair = order.pair();
+        if let Some(pair_orders) = self.active_orders_by_pair.get_mut(&pair) {
+            pair_orders.remove(order_id);
+            if pair_orders.is_empty() {
+                self.active_orders_by_pair.remove(&pair);
+            }
+        }
+        self.open_orders.remove(order_id);
+        if let Some(nonce) = order.nonce {
+            self.internal_invalidate_nonce(&order.maker, nonce);
+        }
+        self.internal_release_escrow(order_id)
+    }
+
+    /// Return whatever is left in the order's escrow to the maker.
+    /// Returns the refunded amount.
+    pub(crate) fn internal_release_escrow(&mut self, order_id: &String) -> u128 {
+        let mut order = self.orders.get(order_id).expect("Order not found").clone();
+
+        let amount = order.escrowed_amount;
+        if amount == 0 {
+            return 0;
+        }
+
+        order.escrowed_amount = 0;
+        self.orders.insert(order_id.clone(), order.clone());
+
+        self.internal_send(
+            &order.maker,
+            &order.maker_asset,
+            amount,
+            format!("Refund order {}", order_id),
+        );
+
+        amount
+    }
+
+    /// Remove a closed order and its index entries once no fill of it is in
+    /// flight. Returns the removed order, whose storage deposit is now free.
+    pub(crate) fn internal_remove_order(&mut self, order_id: &String) -> Option<Order> {
+        let order = self.orders.get(order_id).expect("Order not found");
+        if order.status == OrderStatus::Open || order.fills_in_flight > 0 {
+            return None;
+        }
+        let order = self.orders.remove(order_id)?;
+
+        if let Some(maker_orders) = self.orders_by_maker.get_mut(&order.maker) {
+            maker_orders.remove(order_id);
+            if maker_orders.is_empty() {
+                self.orders_by_maker.remove(&order.maker);
+            }
+        }
+        let pair = order.pair();
+        if let Some(pair_orders) = self.orders_by_pair.get_mut(&pair) {
+            pair_orders.remove(order_id);
+            if pair_orders.is_empty() {
+                self.orders_by_pair.remove(&pair);
+            }
+        }
+
+        Some(order)
+    }
+
+    /// Remove a closed order and unlock its storage deposit in the maker's
+    /// storage balance
+    pub(crate) fn internal_refund_order_storage(&mut self, order_id: &String) {
+        let Some(order) = self.internal_remove_order(order_id) else {
+            return;
+        };
+        self.internal_unlock_storage(&order.maker, order.storage_deposit);
+    }
+
+    /// Write pending changes of the order book records `order` is stored in,
+    /// so the storage they take can be measured
+    fn internal_flush_order_book(&mut self, order: &Order) {
+        let pair = order.pair();
+        if let Some(order_ids) = self.orders_by_maker.get_mut(&order.maker) {
+            order_ids.flush();
+        }
+        if let Some(order_ids) = self.orders_by_pair.get_mut(&pair) {
+            order_ids.flush();
+        }
+        if let Some(order_ids) = self.active_orders_by_pair.get_mut(&pair) {
+            order_ids.flush();
+        }
+        self.orders.flush();
+        self.orders_by_maker.flush();
+        self.orders_by_pair.flush();
+        self.active_orders_by_pair.flush();
+        self.open_orders.flush();
+    }
+
+    pub(crate) fn internal_maker_orders_mut(
+        &mut self,
+        maker: &AccountId,
+    ) -> &mut IterableSet<String> {
+        self.orders_by_maker
+            .entry(maker.clone())
+            .or_insert_with(|| {
+                IterableSet::new(Prefix::MakerOrders {
+                    account_hash: env::sha256_array(maker.as_bytes()),
+                })
+            })
+    }
+
+    pub(crate) fn internal_pair_orders_mut(&mut self, pair: &Pair) -> &mut IterableSet<String> {
+        self.orders_by_pair.entry(pair.clone()).or_insert_with(|| {
+            IterableSet::new(Prefix::PairOrders {
+                pair_hash: pair.hash(),
+            })
+        })
+    }
+
+    pub(crate) fn internal_active_pair_orders_mut(
+        &mut self,
+        pair: &Pair,
+    ) -> &mut IterableSet<String> {
+        self.active_orders_by_pair
+            .entry(pair.clone())
+            .or_insert_with(|| {
+                IterableSet::new(Prefix::ActivePairOrders {
+                    pair_hash: pair.hash(),
+                })
+            })
+    }
+}
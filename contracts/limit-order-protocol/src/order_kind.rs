@@ -0,0 +1,51 @@
+use crate::math::{mul_div, Rounding};
+use crate::*;
+
+impl OrderKind {
+    pub fn assert_valid(&self) {
+        match self {
+            OrderKind::Limit => {}
+            OrderKind::Twap {
+                interval, tranches, ..
+            } => {
+                assert!(*interval > 0, "TWAP interval must be greater than 0");
+                assert!(*tranches > 0, "TWAP tranches must be greater than 0");
+            }
+            OrderKind::Iceberg { clip } => {
+                assert!(clip.0 > 0, "Iceberg clip must be greater than 0");
+            }
+        }
+    }
+}
+
+impl Order {
+    /// Part of the remaining making amount takers can fill now: what has
+    /// unlocked so far for TWAP orders, the visible clip for iceberg orders
+    pub fn fillable_making_amount(&self) -> u128 {
+        let remaining = self.remaining_making_amount();
+        match &self.kind {
+            OrderKind::Limit => remaining,
+            OrderKind::Twap {
+                start_time,
+                interval,
+                tranches,
+            } => {
+                let now = env::block_timestamp();
+                if now < *start_time {
+                    return 0;
+                }
+                let unlocked_tranches = ((now - start_time) / interval + 1).min(*tranches as u64);
+                let unlocked = mul_div(
+                    self.making_amount,
+                    unlocked_tranches as u128,
+                    *tranches as u128,
+                    Rounding::Down,
+                );
+                unlocked
+                    .saturating_sub(self.filled_making_amount)
+                    .min(remaining)
+            }
+            OrderKind::Iceberg { clip } => clip.0.min(remaining),
+        }
+    }
+}
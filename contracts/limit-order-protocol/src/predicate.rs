@@ -0,0 +1,388 @@
+use near_sdk::{PromiseOrValue, PromiseResult};
+
+use crate::*;
+
+impl Predicate {
+    /// View calls of the predicate, in the order `evaluate` consumes their results
+    pub(crate) fn view_calls<'a>(&'a self, calls: &mut Vec<&'a ViewCall>) {
+        match self {
+            Predicate::And(predicates) | Predicate::Or(predicates) => {
+                for predicate in predicates.iter() {
+                    predicate.view_calls(calls);
+                }
+            }
+            Predicate::Lt { value, .. }
+            | Predicate::Gt { value, .. }
+            | Predicate::Eq { value, .. } => {
+                if let Operand::View(call) = value {
+                    calls.push(call);
+                }
+            }
+        }
+    }
+
+    /// Gas needed to resolve the predicate's view calls and continue the fill
+    /// with `settle_gas`
+    pub fn resolve_gas(&self, settle_gas: Gas) -> Gas {
+        let mut calls = vec![];
+        self.view_calls(&mut calls);
+        if calls.is_empty() {
+            return Gas::from_gas(0);
+        }
+        GAS_FOR_PREDICATE_VIEW
+            .saturating_mul(calls.len() as u64)
+            .saturating_add(settle_gas)
+    }
+}
+
+impl ViewCall {
+    /// Make the call. It is an ordinary function call, so nothing keeps the
+    /// target from changing its state in it.
+    pub(crate) fn call(&self) -> Promise {
+        Promise::new(self.contract_id.clone()).function_call(
+            self.method_name.clone(),
+            self.args.clone().into_bytes(),
+            NearToken::from_yoctonear(0),
+            GAS_FOR_PREDICATE_VIEW,
+        )
+    }
+}
+
+#[near]
+impl LimitOrderProtocol {
+    /// Callback after the view calls of an order predicate returned
+    #[private]
+    pub fn on_fill_predicate_resolved(&mut self, request: FillRequest) -> PromiseOrValue<U128> {
+        let order = self.orders.get(&request.order_id).expect("Order not found");
+        let predicate = order.predicate.as_ref().expect("Order has no predicate");
+
+        let mut calls = vec![];
+        predicate.view_calls(&mut calls);
+        let mut view_results = calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| match env::promise_result(i as u64) {
+                PromiseResult::Successful(data) => parse_view_result(&data, call.field.as_deref()),
+                _ => None,
+            });
+        if !self.evaluate_predicate(&order.maker, predicate, &mut view_results) {
+            // Refund the taker
+            return PromiseOrValue::Value(request.amount);
+        }
+
+        self.internal_execute_fill(request)
+    }
+}
+
+impl LimitOrderProtocol {
+    /// View calls are made with the protocol as predecessor, so they can only
+    /// reach oracles the owner allowed
+    pub(crate) fn is_predicate_allowed(&self, predicate: &Predicate) -> bool {
+        let mut calls = vec![];
+        predicate.view_calls(&mut calls);
+        calls
+            .iter()
+            .all(|call| self.oracles.contains(&call.contract_id))
+    }
+
+    pub(crate) fn assert_predicate_allowed(&self, predicate: &Predicate) {
+        assert!(
+            self.is_predicate_allowed(predicate),
+            "Predicates can only call allowed oracles"
+        );
+    }
+
+    /// Check the order predicate and settle the fill. View calls in the
+    /// predicate are made first and the fill continues in their callback.
+    pub(crate) fn internal_check_predicate(
+        &mut self,
+        request: FillRequest,
+    ) -> PromiseOrValue<U128> {
+        let order = self.orders.get(&request.order_id).expect("Order not found");
+        let Some(predicate) = order.predicate.as_ref() else {
+            return self.internal_execute_fill(request);
+        };
+        let settle_gas = settle_callback_gas(order, &request);
+
+        let mut calls = vec![];
+        predicate.view_calls(&mut calls);
+        if calls.is_empty() {
+            assert!(
+                self.evaluate_predicate(&order.maker, predicate, &mut std::iter::empty()),
+                "{}",
+                FillRejection::PredicateNotSatisfied.message()
+            );
+            return self.internal_execute_fill(request);
+        }
+
+        calls
+            .into_iter()
+            .map(ViewCall::call)
+            .reduce(|joint, promise| joint.and(promise))
+            .unwrap()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(settle_gas)
+                    .on_fill_predicate_resolved(request),
+            )
+            .into()
+    }
+
+    /// Evaluate a predicate. Children are all evaluated, without short circuit,
+    /// so every view call result is consumed in order.
+    pub(crate) fn evaluate_predicate(
+        &self,
+        maker: &AccountId,
+        predicate: &Predicate,
+        view_results: &mut impl Iterator<Item = Option<u128>>,
+    ) -> bool {
+        match predicate {
+            Predicate::And(predicates) => predicates
+                .iter()
+                .map(|predicate| self.evaluate_predicate(maker, predicate, view_results))
+                .fold(true, |result, value| result && value),
+            Predicate::Or(predicates) => predicates
+                .iter()
+                .map(|predicate| self.evaluate_predicate(maker, predicate, view_results))
+                .fold(false, |result, value| result || value),
+            Predicate::Lt { value, threshold } => self
+                .evaluate_operand(maker, value, view_results)
+                .is_some_and(|value| value < threshold.0),
+            Predicate::Gt { value, threshold } => self
+                .evaluate_operand(maker, value, view_results)
+                .is_some_and(|value| value > threshold.0),
+            Predicate::Eq { value, threshold } => self
+                .evaluate_operand(maker, value, view_results)
+                .is_some_and(|value| value == threshold.0),
+        }
+    }
+
+    fn evaluate_operand(
+        &self,
+        maker: &AccountId,
+        operand: &Operand,
+        view_results: &mut impl Iterator<Item = Option<u128>>,
+    ) -> Option<u128> {
+        match operand {
+            Operand::BlockTimestamp => Some(env::block_timestamp() as u128),
+            Operand::Epoch { series } => Some(self.internal_epoch_of(maker, *series) as u128),
+            Operand::View(_) => view_results.next().flatten(),
+        }
+    }
+}
+
+pub(crate) fn parse_view_result(data: &[u8], field: Option<&str>) -> Option<u128> {
+    let mut value = serde_json::from_slice::<serde_json::Value>(data).ok()?;
+    if let Some(field) = field {
+        value = value.get(field)?.clone();
+    }
+    match value {
+        serde_json::Value::Number(number) => number.to_string().parse().ok(),
+        serde_json::Value::String(string) => string.parse().ok(),
+        serde_json::Value::Bool(boolean) => Some(boolean as u128),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const NOW: u64 = 1_000;
+
+    /// Protocol owned by `accounts(1)`, who is also the caller
+    fn setup() -> LimitOrderProtocol {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(NOW)
+            .build());
+        LimitOrderProtocol::new(accounts(1))
+    }
+
+    fn view() -> Operand {
+        Operand::View(ViewCall {
+            contract_id: accounts(2),
+            method_name: "get_price".to_string(),
+            args: String::new(),
+            field: None,
+        })
+    }
+
+    fn lt(value: Operand, threshold: u128) -> Predicate {
+        Predicate::Lt {
+            value,
+            threshold: U128(threshold),
+        }
+    }
+
+    fn gt(value: Operand, threshold: u128) -> Predicate {
+        Predicate::Gt {
+            value,
+            threshold: U128(threshold),
+        }
+    }
+
+    fn eq(value: Operand, threshold: u128) -> Predicate {
+        Predicate::Eq {
+            value,
+            threshold: U128(threshold),
+        }
+    }
+
+    fn evaluate(
+        contract: &LimitOrderProtocol,
+        predicate: &Predicate,
+        view_results: Vec<Option<u128>>,
+    ) -> bool {
+        contract.evaluate_predicate(&accounts(3), predicate, &mut view_results.into_iter())
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let contract = setup();
+        let now = NOW as u128;
+
+        assert!(evaluate(
+            &contract,
+            &lt(Operand::BlockTimestamp, now + 1),
+            vec![]
+        ));
+        assert!(!evaluate(
+            &contract,
+            &lt(Operand::BlockTimestamp, now),
+            vec![]
+        ));
+        assert!(evaluate(
+            &contract,
+            &gt(Operand::BlockTimestamp, now - 1),
+            vec![]
+        ));
+        assert!(!evaluate(
+            &contract,
+            &gt(Operand::BlockTimestamp, now),
+            vec![]
+        ));
+        assert!(evaluate(
+            &contract,
+            &eq(Operand::BlockTimestamp, now),
+            vec![]
+        ));
+        assert!(!evaluate(
+            &contract,
+            &eq(Operand::BlockTimestamp, now + 1),
+            vec![]
+        ));
+        assert!(evaluate(
+            &contract,
+            &eq(Operand::Epoch { series: 0 }, 0),
+            vec![]
+        ));
+    }
+
+    #[test]
+    fn test_failed_view_does_not_hold() {
+        let contract = setup();
+
+        assert!(evaluate(&contract, &lt(view(), 10), vec![Some(9)]));
+        assert!(!evaluate(&contract, &lt(view(), 10), vec![None]));
+        assert!(!evaluate(&contract, &gt(view(), 10), vec![None]));
+        assert!(!evaluate(&contract, &eq(view(), 0), vec![None]));
+    }
+
+    #[test]
+    fn test_and() {
+        let contract = setup();
+        let predicate = Predicate::And(vec![gt(view(), 10), lt(view(), 20)]);
+
+        assert!(evaluate(&contract, &predicate, vec![Some(15), Some(15)]));
+        assert!(!evaluate(&contract, &predicate, vec![Some(5), Some(15)]));
+        assert!(!evaluate(&contract, &predicate, vec![Some(15), Some(25)]));
+        assert!(evaluate(&contract, &Predicate::And(vec![]), vec![]));
+    }
+
+    #[test]
+    fn test_or() {
+        let contract = setup();
+        let predicate = Predicate::Or(vec![lt(view(), 10), gt(view(), 20)]);
+
+        assert!(evaluate(&contract, &predicate, vec![Some(5), Some(15)]));
+        assert!(evaluate(&contract, &predicate, vec![Some(15), Some(25)]));
+        assert!(!evaluate(&contract, &predicate, vec![Some(15), Some(15)]));
+        assert!(!evaluate(&contract, &Predicate::Or(vec![]), vec![]));
+    }
+
+    #[test]
+    fn test_view_results_consumed_in_order() {
+        let contract = setup();
+        // The first branch holds, the second one still takes its result
+        let predicate = Predicate::And(vec![
+            Predicate::Or(vec![eq(view(), 1), eq(view(), 2)]),
+            eq(view(), 3),
+        ]);
+
+        assert!(evaluate(
+            &contract,
+            &predicate,
+            vec![Some(1), Some(0), Some(3)]
+        ));
+        assert!(!evaluate(
+            &contract,
+            &predicate,
+            vec![Some(1), Some(3), Some(0)]
+        ));
+    }
+
+    #[test]
+    fn test_parse_view_result() {
+        assert_eq!(parse_view_result(b"42", None), Some(42));
+        assert_eq!(
+            parse_view_result(b"\"340282366920938463463374607431768211455\"", None),
+            Some(u128::MAX)
+        );
+        assert_eq!(parse_view_result(b"true", None), Some(1));
+        assert_eq!(parse_view_result(b"false", None), Some(0));
+        assert_eq!(
+            parse_view_result(br#"{"price":"7","open":true}"#, Some("price")),
+            Some(7)
+        );
+        assert_eq!(
+            parse_view_result(br#"{"price":"7","open":true}"#, Some("open")),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_view_result() {
+        assert_eq!(parse_view_result(br#"{"price":"7"}"#, Some("volume")), None);
+        assert_eq!(parse_view_result(br#"{"price":"7"}"#, None), None);
+        assert_eq!(parse_view_result(b"-1", None), None);
+        assert_eq!(parse_view_result(b"1.5", None), None);
+        assert_eq!(parse_view_result(b"\"abc\"", None), None);
+        assert_eq!(parse_view_result(b"null", None), None);
+        assert_eq!(parse_view_result(b"not json", None), None);
+    }
+
+    #[test]
+    fn test_predicate_oracles() {
+        let mut contract = setup();
+        let predicate = Predicate::And(vec![lt(Operand::BlockTimestamp, 1), eq(view(), 1)]);
+
+        assert!(!contract.is_predicate_allowed(&predicate));
+        contract.add_oracle(accounts(2));
+        assert!(contract.is_predicate_allowed(&predicate));
+        contract.remove_oracle(accounts(2));
+        assert!(!contract.is_predicate_allowed(&predicate));
+        assert!(contract.is_predicate_allowed(&lt(Operand::BlockTimestamp, 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Predicates cannot call the protocol")]
+    fn test_protocol_is_not_an_oracle() {
+        let mut contract = setup();
+        contract.add_oracle(accounts(0));
+    }
+}
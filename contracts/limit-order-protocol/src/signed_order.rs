@@ -0,0 +1,140 @@
+use near_sdk::{assert_one_yocto, json_types::Base64VecU8, CurveType};
+
+use crate::*;
+
+/// NEP-413 payloads are prefixed with 2^31 + 413 so a signed message can never
+/// be a valid transaction
+const NEP413_TAG: u32 = (1 << 31) + 413;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Register an ed25519 key the caller signs off-chain orders with. The key
+    /// is paid from the caller's storage balance.
+    #[payable]
+    pub fn add_signing_key(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        assert!(
+            public_key.curve_type() == CurveType::ED25519,
+            "Only ed25519 keys are supported"
+        );
+
+        let account_id = env::predecessor_account_id();
+        self.signing_keys.flush();
+        let initial_usage = env::storage_usage();
+        let keys = self.signing_keys.entry(account_id.clone()).or_default();
+        if !keys.contains(&public_key) {
+            keys.push(public_key);
+        }
+        self.signing_keys.flush();
+        self.internal_charge_storage(&account_id, initial_usage);
+        self.assert_storage_paid(&account_id);
+    }
+
+    /// Remove a signing key. Signed orders that were not filled yet can no
+    /// longer be submitted with it.
+    #[payable]
+    pub fn remove_signing_key(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        self.signing_keys.flush();
+        let initial_usage = env::storage_usage();
+        if let Some(keys) = self.signing_keys.get_mut(&account_id) {
+            keys.retain(|key| key != &public_key);
+            if keys.is_empty() {
+                self.signing_keys.remove(&account_id);
+            }
+        }
+        self.signing_keys.flush();
+        self.internal_charge_storage(&account_id, initial_usage);
+    }
+
+    /// Get the signing keys registered by an account
+    pub fn get_signing_keys(&self, account_id: AccountId) -> Vec<PublicKey> {
+        self.signing_keys
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Store the order described by `signed_order` under `order_id` if it was
+    /// not created by an earlier fill, locking the making amount from the
+    /// maker's balance.
+    pub(crate) fn internal_create_signed_order(
+        &mut self,
+        order_id: &String,
+        signed_order: SignedOrder,
+    ) {
+        let order_hash = signed_order.order.hash();
+        assert!(
+            *order_id == hex::encode(order_hash),
+            "Order ID does not match the signed order"
+        );
+        if self.orders.contains_key(order_id) {
+            return;
+        }
+
+        let data = signed_order.order;
+        assert!(
+            !self.internal_is_nonce_invalidated(&data.maker, data.nonce),
+            "Order nonce is invalidated"
+        );
+        self.assert_order_signature(
+            &data.maker,
+            &order_hash,
+            &signed_order.public_key,
+            &signed_order.signature,
+        );
+
+        let maker = data.maker.clone();
+        let maker_asset = data.maker_asset.clone();
+        let making_amount = data.making_amount.0;
+        let nonce = data.nonce;
+
+        // Lock the maker funds for the order
+        self.internal_withdraw_balance(&maker, &maker_asset, making_amount);
+        self.internal_create_order(order_id.clone(), data, Some(nonce));
+        self.internal_fund_order(order_id, &maker_asset, &maker, making_amount);
+    }
+
+    fn assert_order_signature(
+        &self,
+        maker: &AccountId,
+        order_hash: &[u8; 32],
+        public_key: &PublicKey,
+        signature: &Base64VecU8,
+    ) {
+        assert!(
+            self.signing_keys
+                .get(maker)
+                .is_some_and(|keys| keys.contains(public_key)),
+            "Signing key is not registered by the maker"
+        );
+
+        let signature: [u8; 64] = signature
+            .0
+            .as_slice()
+            .try_into()
+            .expect("Invalid signature length");
+        // The first byte of the key data is the curve type
+        let public_key: [u8; 32] = public_key.as_bytes()[1..]
+            .try_into()
+            .expect("Invalid public key length");
+
+        let payload = Nep413Payload {
+            message: hex::encode(order_hash),
+            nonce: *order_hash,
+            recipient: env::current_account_id().to_string(),
+            callback_url: None,
+        };
+        let mut message = near_sdk::borsh::to_vec(&NEP413_TAG).unwrap();
+        message.extend(near_sdk::borsh::to_vec(&payload).unwrap());
+
+        assert!(
+            env::ed25519_verify(&signature, &env::sha256_array(&message), &public_key),
+            "Invalid order signature"
+        );
+    }
+}
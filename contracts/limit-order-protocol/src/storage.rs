@@ -0,0 +1,214 @@
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::assert_one_yocto;
+
+use crate::*;
+
+/// Bytes of an account's storage record: the record overhead, its key and
+/// the locked and total amounts
+const ACCOUNT_STORAGE_BYTES: u64 = 40 + 1 + 4 + 64 + 32;
+
+#[near]
+impl StorageManagement for LimitOrderProtocol {
+    /// Deposit NEAR paying for the orders of `account_id`, the caller by
+    /// default. Registering takes at least the minimum of
+    /// `storage_balance_bounds`, and `registration_only` refunds everything
+    /// above it.
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit().as_yoctonear();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+
+        let deposit = if registration_only == Some(true) {
+            let min = if self.storage_balances.contains_key(&account_id) {
+                0
+            } else {
+                account_storage_cost()
+            };
+            let refund = amount
+                .checked_sub(min)
+                .expect("Attached deposit is less than the minimum storage balance");
+            if refund > 0 {
+                Promise::new(env::predecessor_account_id())
+                    .transfer(NearToken::from_yoctonear(refund));
+            }
+            min
+        } else {
+            amount
+        };
+        self.internal_storage_deposit(&account_id, deposit);
+
+        self.internal_storage_balance_of(&account_id)
+            .expect("Account is not registered")
+    }
+
+    /// Withdraw NEAR not locked by the caller's orders, all of it by default
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        let mut storage = self
+            .storage_balances
+            .get(&account_id)
+            .expect("Account is not registered")
+            .clone();
+        let available = storage.available();
+        let amount = amount.map(NearToken::as_yoctonear).unwrap_or(available);
+        assert!(
+            amount <= available,
+            "Amount exceeds the available storage balance"
+        );
+
+        storage.total -= amount;
+        self.storage_balances.insert(account_id.clone(), storage);
+        if amount > 0 {
+            Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(amount));
+        }
+
+        self.internal_storage_balance_of(&account_id)
+            .expect("Account is not registered")
+    }
+
+    /// Close the caller's storage account and refund its balance. Accounts
+    /// with stored orders cannot be closed, `force` is not supported.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        assert!(force != Some(true), "Force unregistering is not supported");
+
+        let account_id = env::predecessor_account_id();
+        let Some(storage) = self.storage_balances.get(&account_id) else {
+            return false;
+        };
+        assert!(
+            storage.locked == account_storage_cost(),
+            "Account still has stored orders or records"
+        );
+
+        let total = storage.total;
+        self.storage_balances.remove(&account_id);
+        Promise::new(account_id).transfer(NearToken::from_yoctonear(total));
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: NearToken::from_yoctonear(account_storage_cost()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.internal_storage_balance_of(&account_id)
+    }
+}
+
+impl LimitOrderProtocol {
+    /// Add `amount` to the storage balance of `account_id`, registering the
+    /// account if needed
+    pub(crate) fn internal_storage_deposit(&mut self, account_id: &AccountId, amount: u128) {
+        let min = account_storage_cost();
+        let storage = self
+            .storage_balances
+            .entry(account_id.clone())
+            .or_insert_with(|| {
+                assert!(
+                    amount >= min,
+                    "Attached deposit is less than the minimum storage balance"
+                );
+                AccountStorage {
+                    total: 0,
+                    locked: min,
+                }
+            });
+        storage.total += amount;
+    }
+
+    /// Lock `amount` of the storage balance of `account_id`
+    pub(crate) fn internal_lock_storage(&mut self, account_id: &AccountId, amount: u128) {
+        let storage = self
+            .storage_balances
+            .get_mut(account_id)
+            .expect("Maker is not registered for storage");
+        assert!(
+            amount <= storage.available(),
+            "Not enough storage balance for the order, {} yoctoNEAR needed",
+            amount
+        );
+        storage.locked += amount;
+    }
+
+    /// Unlock `amount` of the storage balance of `account_id`
+    pub(crate) fn internal_unlock_storage(&mut self, account_id: &AccountId, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        let storage = self
+            .storage_balances
+            .get_mut(account_id)
+            .expect("Account is not registered");
+        storage.locked -= amount;
+    }
+
+    /// Charge `account_id` for what the storage usage grew by since
+    /// `initial_usage`, or unlock what it shrank by. Store collections write
+    /// their changes when flushed, so callers flush the ones they changed
+    /// first. What the account cannot pay is owed and taken from its next
+    /// deposits, callers the account must pay for check `assert_storage_paid`.
+    pub(crate) fn internal_charge_storage(&mut self, account_id: &AccountId, initial_usage: u64) {
+        let usage = env::storage_usage();
+        let min = account_storage_cost();
+        if usage > initial_usage {
+            let storage =
+                self.storage_balances
+                    .entry(account_id.clone())
+                    .or_insert(AccountStorage {
+                        total: 0,
+                        locked: min,
+                    });
+            storage.locked += storage_cost_since(initial_usage);
+        } else if let Some(storage) = self.storage_balances.get_mut(account_id) {
+            let freed = (initial_usage - usage) as u128 * env::storage_byte_cost().as_yoctonear();
+            storage.locked = storage.locked.saturating_sub(freed).max(min);
+        }
+    }
+
+    /// Check the storage balance of `account_id` covers all it stores
+    pub(crate) fn assert_storage_paid(&self, account_id: &AccountId) {
+        let storage = self
+            .storage_balances
+            .get(account_id)
+            .expect("Account is not registered for storage");
+        assert!(
+            storage.locked <= storage.total,
+            "Not enough storage balance, {} yoctoNEAR needed",
+            storage.locked - storage.total
+        );
+    }
+
+    fn internal_storage_balance_of(&self, account_id: &AccountId) -> Option<StorageBalance> {
+        self.storage_balances
+            .get(account_id)
+            .map(|storage| StorageBalance {
+                total: NearToken::from_yoctonear(storage.total),
+                available: NearToken::from_yoctonear(storage.available()),
+            })
+    }
+}
+
+/// NEAR it costs to store what the storage usage grew by since `initial_usage`
+pub(crate) fn storage_cost_since(initial_usage: u64) -> u128 {
+    env::storage_usage().saturating_sub(initial_usage) as u128
+        * env::storage_byte_cost().as_yoctonear()
+}
+
+/// NEAR it costs to store an account's storage record
+fn account_storage_cost() -> u128 {
+    ACCOUNT_STORAGE_BYTES as u128 * env::storage_byte_cost().as_yoctonear()
+}
@@ -0,0 +1,59 @@
+use crate::*;
+
+/// Share of a swept order's storage deposit paid to the sweeper, 10%
+const SWEEP_BOUNTY_BPS: u32 = 1_000;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Expire open orders past their expiration: refund their escrow, remove
+    /// them and unlock their storage deposit in the maker's storage balance.
+    /// Checks up to `limit` open orders, starting where the previous sweep
+    /// stopped, and pays the caller `SWEEP_BOUNTY_BPS` of every storage
+    /// deposit freed. Returns the number of orders expired.
+    pub fn sweep_expired(&mut self, limit: u32) -> u32 {
+        let mut cursor = self.sweep_cursor;
+        if cursor >= self.open_orders.len() {
+            cursor = 0;
+        }
+        let order_ids: Vec<String> = self
+            .open_orders
+            .iter()
+            .skip(cursor as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+        // Orders moved into the place of expired ones are checked once the
+        // cursor wraps around
+        self.sweep_cursor = cursor + order_ids.len() as u32;
+
+        let mut expired = 0;
+        let mut bounty = 0;
+        for order_id in order_ids.iter() {
+            let order = self.orders.get(order_id).expect("Order not found");
+            if order.status != OrderStatus::Open || !order.is_expired() {
+                continue;
+            }
+
+            self.internal_expire_order(order_id);
+            expired += 1;
+
+            // Orders with fills in flight are removed, with their full
+            // deposit, once the fills are done
+            let Some(order) = self.internal_remove_order(order_id) else {
+                continue;
+            };
+            let order_bounty = fee_amount(order.storage_deposit, SWEEP_BOUNTY_BPS);
+            self.internal_unlock_storage(&order.maker, order.storage_deposit);
+            if let Some(storage) = self.storage_balances.get_mut(&order.maker) {
+                storage.total -= order_bounty;
+            }
+            bounty += order_bounty;
+        }
+
+        if bounty > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(NearToken::from_yoctonear(bounty));
+        }
+
+        expired
+    }
+}
@@ -0,0 +1,106 @@
+use near_sdk::{near, AccountId, PromiseOrValue};
+
+use crate::*;
+
+const ERR_MALFORMED_MESSAGE: &str = "Invalid transfer action message";
+
+#[near(serializers = [json])]
+#[serde(rename_all = "snake_case")]
+enum TokenReceiverMessage {
+    /// Add the tokens to the sender's unlocked balance. A new balance record
+    /// is paid from the sender's storage balance.
+    Deposit,
+    /// Escrow the tokens for an existing order of the sender
+    FundOrder { order_id: String },
+    /// Create a new fully funded order selling the transferred tokens
+    CreateOrder { order: OrderData },
+    /// Fill an order with the transferred taker asset. Signed orders that are
+    /// not stored yet are created from `signed_order` on their first fill.
+    Fill {
+        order_id: String,
+        /// Buy this much maker asset, refunding the unspent taker asset
+        #[serde(default)]
+        making_amount: Option<U128>,
+        /// Least maker asset to receive, or most taker asset to pay when
+        /// `making_amount` is set
+        #[serde(default)]
+        threshold: Option<U128>,
+        #[serde(default)]
+        signed_order: Option<SignedOrder>,
+        /// Taker interaction run during settlement
+        #[serde(default)]
+        interaction: Option<Interaction>,
+        /// Receive a wNEAR maker asset as native NEAR
+        #[serde(default)]
+        unwrap_near: bool,
+    },
+}
+
+#[near]
+impl LimitOrderProtocol {
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        if msg.is_empty() {
+            // refund all
+            return PromiseOrValue::Value(amount);
+        }
+
+        let token_id = env::predecessor_account_id();
+        self.custodied_tokens.insert(token_id.clone());
+        let message =
+            serde_json::from_str::<TokenReceiverMessage>(&msg).expect(ERR_MALFORMED_MESSAGE);
+        match message {
+            TokenReceiverMessage::Deposit => {
+                self.internal_deposit_balance(&sender_id, &token_id, amount.0);
+                self.assert_storage_paid(&sender_id);
+                PromiseOrValue::Value(U128(0))
+            }
+            TokenReceiverMessage::FundOrder { order_id } => {
+                let used = self.internal_fund_order(&order_id, &token_id, &sender_id, amount.0);
+                // refund the amount exceeding the order size
+                PromiseOrValue::Value(U128(amount.0 - used))
+            }
+            TokenReceiverMessage::CreateOrder { order } => {
+                assert!(order.maker == sender_id, "Only maker can create order");
+                assert!(
+                    order.maker_asset == token_id,
+                    "Token is not the order's maker asset"
+                );
+                assert!(
+                    order.making_amount == amount,
+                    "Transferred amount must equal the making amount"
+                );
+                let order_id = self.internal_next_order_id();
+                self.internal_create_order(order_id.clone(), order, None);
+                self.internal_fund_order(&order_id, &token_id, &sender_id, amount.0);
+                PromiseOrValue::Value(U128(0))
+            }
+            TokenReceiverMessage::Fill {
+                order_id,
+                making_amount,
+                threshold,
+                signed_order,
+                interaction,
+                unwrap_near,
+            } => {
+                if let Some(signed_order) = signed_order {
+                    self.internal_create_signed_order(&order_id, signed_order);
+                }
+                self.internal_fill_order(FillRequest {
+                    order_id,
+                    token_id,
+                    taker: sender_id,
+                    amount,
+                    making_amount,
+                    threshold,
+                    interaction,
+                    unwrap_near,
+                })
+            }
+        }
+    }
+}
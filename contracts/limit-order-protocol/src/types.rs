@@ -1,20 +1,364 @@
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::{Base64VecU8, U128},
     serde::{Deserialize, Serialize},
-    AccountId, Timestamp,
+    near, AccountId, BorshStorageKey, Gas, PublicKey, Timestamp,
 };
 
+#[near]
+#[derive(BorshStorageKey)]
+pub enum Prefix {
+    Orders,
+    OrdersByMaker,
+    MakerOrders { account_hash: [u8; 32] },
+    OrdersByPair,
+    PairOrders { pair_hash: [u8; 32] },
+    ActiveOrdersByPair,
+    ActivePairOrders { pair_hash: [u8; 32] },
+    Balances,
+    SigningKeys,
+    NonceBitmaps,
+    Epochs,
+    FeeBalances,
+    OpenOrders,
+    StorageBalances,
+    CustodiedTokens,
+    Oracles,
+}
+
+/// Canonical order description. Its borsh serialization is what gets hashed
+/// and signed by makers for off-chain orders.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+pub struct OrderData {
+    pub maker: AccountId,
+    pub maker_asset: AccountId,
+    pub taker_asset: AccountId,
+    pub making_amount: U128,
+    pub taking_amount: U128,
+    pub expiration: Timestamp,
+    /// Used by signed orders for replay protection and bulk cancellation
+    /// through `cancel_orders`
+    pub nonce: u64,
+    /// Order series, all orders of a series are cancelled by `increase_epoch`
+    #[serde(default)]
+    pub series: u64,
+    /// Must match the maker's current epoch for `series`
+    #[serde(default)]
+    pub epoch: u64,
+    /// Dutch auction raising the taking amount above `taking_amount` while it runs
+    #[serde(default)]
+    pub auction: Option<AuctionDetails>,
+    /// Only this account can fill the order
+    #[serde(default)]
+    pub allowed_taker: Option<AccountId>,
+    /// Only workers with the `LimitOrder` permission in the solver registry can fill the order
+    #[serde(default)]
+    pub solvers_only: bool,
+    /// Condition that must hold at fill time
+    #[serde(default)]
+    pub predicate: Option<Predicate>,
+    /// Called during settlement before the taker's interaction
+    #[serde(default)]
+    pub pre_interaction: Option<Interaction>,
+    /// Called once the fill is settled and paid out. Its failure does not
+    /// revert the fill.
+    #[serde(default)]
+    pub post_interaction: Option<Interaction>,
+    /// Fee paid to the integrator that brought the order, out of the taker amount
+    #[serde(default)]
+    pub integrator_fee: Option<IntegratorFee>,
+    /// How the making amount is released to takers
+    #[serde(default)]
+    pub kind: OrderKind,
+    /// Where the taker asset paid to the maker goes, the maker by default
+    #[serde(default)]
+    pub receiver: Option<Receiver>,
+}
+
+/// How much of an order takers can fill at a time
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum OrderKind {
+    /// The whole remaining amount can be filled
+    #[default]
+    Limit,
+    /// The making amount unlocks in `tranches` equal parts, the first at
+    /// `start_time` and the next ones every `interval` nanoseconds after it
+    Twap {
+        start_time: Timestamp,
+        interval: u64,
+        tranches: u32,
+    },
+    /// Only `clip` of the remaining amount is shown and fillable at a time.
+    /// The funding transfers into the protocol are public, so the total is
+    /// hidden from the book, not from whoever reads the token's history.
+    Iceberg { clip: U128 },
+}
+
+/// Destination of the maker's proceeds
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Receiver {
+    /// Sent to the account with `ft_transfer`
+    Account(AccountId),
+    /// Deposited into a NEAR Intents contract with `ft_transfer_call`, `msg`
+    /// naming the account credited there
+    Intents { contract_id: AccountId, msg: String },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct IntegratorFee {
+    pub recipient: AccountId,
+    pub fee_bps: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProtocolFee {
+    pub recipient: AccountId,
+    pub fee_bps: u32,
+}
+
+/// Function call made by the protocol while settling a fill, without a
+/// deposit. Pre and taker interactions run after the fill is accounted and
+/// before any tokens leave the protocol, and a failing one reverts the fill.
+/// Post interactions run once the fill is paid out and can no longer revert
+/// it. They cannot target the protocol itself, wNEAR or a token the protocol
+/// holds.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Interaction {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    /// JSON encoded arguments
+    #[serde(default)]
+    pub args: String,
+    pub gas: Gas,
+}
+
+/// Boolean expression evaluated before each fill
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Predicate {
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Lt { value: Operand, threshold: U128 },
+    Gt { value: Operand, threshold: U128 },
+    Eq { value: Operand, threshold: U128 },
+}
+
+/// Value compared by a predicate leaf
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operand {
+    BlockTimestamp,
+    /// Maker's current epoch of `series`
+    Epoch { series: u64 },
+    /// Result of a view call to another contract
+    View(ViewCall),
+}
+
+/// Call whose result a predicate reads. Contract calls cannot be made as
+/// views, so it is a function call made by the protocol with nothing
+/// attached: the target must be one of the oracles the owner trusts not to
+/// change state in it.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ViewCall {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    /// JSON encoded arguments
+    #[serde(default)]
+    pub args: String,
+    /// Top level field of the JSON result to compare, the whole result if unset.
+    /// The value must be a number, a decimal string or a boolean.
+    #[serde(default)]
+    pub field: Option<String>,
+}
+
+/// Fusion style auction. The rate bump starts at `initial_rate_bump`, moves
+/// linearly through `points` and reaches 0 at `start_time + duration`.
+/// Rate bumps are expressed in units of `1 / RATE_BUMP_DENOMINATOR`.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuctionDetails {
+    pub start_time: Timestamp,
+    pub duration: u64,
+    pub initial_rate_bump: u32,
+    pub points: Vec<AuctionPoint>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuctionPoint {
+    /// Time since the previous point, or since `start_time` for the first one
+    pub delay: u64,
+    pub rate_bump: u32,
+}
+
+/// Price of an order at the current block
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderRate {
+    pub rate_bump: u64,
+    pub making_amount: U128,
+    /// Taking amount for the whole remaining making amount, auction bump included
+    pub taking_amount: U128,
+}
+
+/// Fill requested by a taker through `ft_transfer_call`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FillRequest {
+    pub order_id: String,
+    /// Token the taker transferred
+    pub token_id: AccountId,
+    pub taker: AccountId,
+    /// Amount the taker transferred, spent on the order unless
+    /// `making_amount` is set
+    pub amount: U128,
+    /// Amount of maker asset to buy, paid out of `amount`
+    pub making_amount: Option<U128>,
+    /// Least maker asset to receive when spending `amount`, or most taker
+    /// asset to pay for `making_amount`
+    pub threshold: Option<U128>,
+    /// Called during settlement after the maker's pre interaction
+    pub interaction: Option<Interaction>,
+    /// Pay out a wNEAR maker asset as native NEAR
+    pub unwrap_near: bool,
+}
+
+/// Mirror of the solver registry's `SolverType`, limited to what is used here
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SolverType {
+    LimitOrder,
+}
+
+/// Why a fill cannot go through
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum FillRejection {
+    OrderNotOpen,
+    OrderExpired,
+    WrongTakerAsset,
+    TakerNotAllowed,
+    NothingFillable,
+    AmountTooSmall,
+    NotEnoughTakerAsset,
+    TakingAmountAboveThreshold,
+    MakingAmountBelowThreshold,
+    NotFunded,
+    PredicateNotSatisfied,
+    CannotUnwrap,
+}
+
+/// Outcome of a fill as it would be settled now
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FillQuote {
+    pub maker_amount: U128,
+    pub taker_amount: U128,
+    pub protocol_fee: U128,
+    pub integrator_fee: U128,
+    /// Amounts left in the order once the fill is settled, only the next
+    /// clip of iceberg orders
+    pub remaining_making_amount: U128,
+    pub remaining_taking_amount: U128,
+    pub is_expired: bool,
+    /// `None` when the order is restricted to registered solvers, which a
+    /// view cannot ask the solver registry about
+    pub taker_permitted: Option<bool>,
+    /// `None` when the predicate reads other contracts, which a view cannot
+    /// call
+    pub predicate_holds: Option<bool>,
+    /// Why the fill would be rejected, `None` if it would be settled
+    pub rejection: Option<FillRejection>,
+}
+
+/// Fill in flight between the maker payout and its callback
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingFill {
+    pub order_id: String,
+    pub taker: AccountId,
+    pub maker_amount: U128,
+    /// Amount of taker asset paid for this fill
+    pub taker_amount: U128,
+    /// Parts of `taker_amount` kept as fees instead of paid to the maker
+    pub protocol_fee: U128,
+    pub integrator_fee: U128,
+    /// The maker asset is paid out as native NEAR
+    pub unwrap_near: bool,
+}
+
+/// One fill of a `fill_orders` batch, paid from the taker's balance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FillInstruction {
+    pub order_id: String,
+    /// Taker asset to spend on the order, or the most to spend when
+    /// `making_amount` is set
+    pub amount: U128,
+    /// Amount of maker asset to buy instead of spending all of `amount`
+    #[serde(default)]
+    pub making_amount: Option<U128>,
+    /// Same as for single fills, aborts the batch if the price got worse
+    #[serde(default)]
+    pub threshold: Option<U128>,
+    /// Creates the order on its first fill, as for single fills
+    #[serde(default)]
+    pub signed_order: Option<SignedOrder>,
+}
+
+/// Fills settled together, all or none of them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingBatch {
+    pub taker: AccountId,
+    pub fills: Vec<PendingFill>,
+    /// Net amounts taken from the taker's balance, by token
+    pub debited: Vec<(AccountId, U128)>,
+}
+
+/// Order signed off-chain by the maker following NEP-413
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+pub struct SignedOrder {
+    pub order: OrderData,
+    /// ed25519 key registered by the maker through `add_signing_key`
+    pub public_key: PublicKey,
+    pub signature: Base64VecU8,
+}
+
+/// NEP-413 message payload. The signed message is the hex order hash, and the
+/// order hash doubles as the NEP-413 nonce.
+#[derive(BorshSerialize)]
+pub struct Nep413Payload {
+    pub message: String,
+    pub nonce: [u8; 32],
+    pub recipient: String,
+    pub callback_url: Option<String>,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
 pub struct Order {
     pub id: String,
     pub maker_asset: AccountId,
     pub taker_asset: AccountId,
+    /// Order terms as created, fills are priced against them
     pub making_amount: u128,
     pub taking_amount: u128,
+    /// Part of `making_amount` already sold
+    pub filled_making_amount: u128,
     pub maker: AccountId,
     pub expiration: Timestamp,
     pub status: OrderStatus,
     pub created_at: Timestamp,
+    /// Amount of `maker_asset` held by the protocol for this order
+    pub escrowed_amount: u128,
+    /// NEAR paid for storing the order, returned when it is removed
+    pub storage_deposit: u128,
+    /// Fills reserved but not settled or reverted yet. A closed order is kept
+    /// until they are done.
+    pub fills_in_flight: u32,
+    /// Nonce of the signed order this order was created from
+    pub nonce: Option<u64>,
+    pub series: u64,
+    pub epoch: u64,
+    pub auction: Option<AuctionDetails>,
+    pub allowed_taker: Option<AccountId>,
+    pub solvers_only: bool,
+    pub predicate: Option<Predicate>,
+    pub pre_interaction: Option<Interaction>,
+    pub post_interaction: Option<Interaction>,
+    pub integrator_fee: Option<IntegratorFee>,
+    pub kind: OrderKind,
+    pub receiver: Option<Receiver>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
@@ -24,10 +368,42 @@ pub struct OrderInfo {
     pub taker_asset: AccountId,
     pub making_amount: u128,
     pub taking_amount: u128,
+    pub filled_making_amount: u128,
+    pub remaining_making_amount: u128,
+    pub remaining_taking_amount: u128,
     pub maker: AccountId,
     pub expiration: Timestamp,
     pub status: OrderStatus,
     pub created_at: Timestamp,
+    pub escrowed_amount: u128,
+    pub storage_deposit: u128,
+    pub nonce: Option<u64>,
+    pub series: u64,
+    pub epoch: u64,
+    pub auction: Option<AuctionDetails>,
+    pub allowed_taker: Option<AccountId>,
+    pub solvers_only: bool,
+    pub predicate: Option<Predicate>,
+    pub pre_interaction: Option<Interaction>,
+    pub post_interaction: Option<Interaction>,
+    pub integrator_fee: Option<IntegratorFee>,
+    pub kind: OrderKind,
+    pub receiver: Option<Receiver>,
+}
+
+/// NEAR an account deposited for storing its orders
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct AccountStorage {
+    pub total: u128,
+    /// Part of `total` paying for the account record and its stored orders
+    pub locked: u128,
+}
+
+/// Market an order trades in, from the maker's side
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pair {
+    pub maker_asset: AccountId,
+    pub taker_asset: AccountId,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -38,16 +414,91 @@ pub enum OrderStatus {
     Expired,
 }
 
+impl OrderData {
+    /// sha256 of the borsh serialized order
+    pub fn hash(&self) -> [u8; 32] {
+        near_sdk::env::sha256_array(&borsh::to_vec(self).expect("Failed to serialize order"))
+    }
+}
+
+impl AccountStorage {
+    /// Balance not locked, nothing while the account owes storage
+    pub fn available(&self) -> u128 {
+        self.total.saturating_sub(self.locked)
+    }
+}
+
+impl Pair {
+    /// sha256 of the borsh serialized pair, used to prefix its index collections
+    pub fn hash(&self) -> [u8; 32] {
+        near_sdk::env::sha256_array(&borsh::to_vec(self).expect("Failed to serialize pair"))
+    }
+}
+
 impl Order {
+    pub fn pair(&self) -> Pair {
+        Pair {
+            maker_asset: self.maker_asset.clone(),
+            taker_asset: self.taker_asset.clone(),
+        }
+    }
+
+    /// Orders expire at their expiration timestamp, every check goes through here
     pub fn is_expired(&self) -> bool {
-        self.expiration < near_sdk::env::block_timestamp()
+        near_sdk::env::block_timestamp() >= self.expiration
     }
 
     pub fn remaining_making_amount(&self) -> u128 {
-        self.making_amount
+        self.making_amount - self.filled_making_amount
     }
 
+    /// Taking amount of the remaining making amount at the order's base
+    /// price, rounded up in the maker's favour
     pub fn remaining_taking_amount(&self) -> u128 {
-        self.taking_amount
+        crate::math::mul_div(
+            self.remaining_making_amount(),
+            self.taking_amount,
+            self.making_amount,
+            crate::math::Rounding::Up,
+        )
     }
-} 
\ No newline at end of file
+
+    /// Maker funds still missing before the order is fully backed
+    pub fn unfunded_amount(&self) -> u128 {
+        self.remaining_making_amount()
+            .saturating_sub(self.escrowed_amount)
+    }
+} 
+
+impl From<&Order> for OrderInfo {
+    fn from(order: &Order) -> Self {
+        Self {
+            id: order.id.clone(),
+            maker_asset: order.maker_asset.clone(),
+            taker_asset: order.taker_asset.clone(),
+            making_amount: order.making_amount,
+            taking_amount: order.taking_amount,
+            filled_making_amount: order.filled_making_amount,
+            remaining_making_amount: order.remaining_making_amount(),
+            remaining_taking_amount: order.remaining_taking_amount(),
+            maker: order.maker.clone(),
+            expiration: order.expiration,
+            status: order.status.clone(),
+            created_at: order.created_at,
+            escrowed_amount: order.escrowed_amount,
+            storage_deposit: order.storage_deposit,
+            nonce: order.nonce,
+            series: order.series,
+            epoch: order.epoch,
+            auction: order.auction.clone(),
+            allowed_taker: order.allowed_taker.clone(),
+            solvers_only: order.solvers_only,
+            predicate: order.predicate.clone(),
+            pre_interaction: order.pre_interaction.clone(),
+            post_interaction: order.post_interaction.clone(),
+            integrator_fee: order.integrator_fee.clone(),
+            kind: order.kind.clone(),
+            receiver: order.receiver.clone(),
+        }
+    }
+}
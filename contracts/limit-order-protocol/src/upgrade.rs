@@ -0,0 +1,145 @@
+use near_sdk::{assert_one_yocto, GasWeight, PromiseOrValue};
+
+use crate::*;
+
+/// Storage key of the state layout version. States written before the key
+/// existed are the V0 layout.
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+/// Version of the current state layout
+pub(crate) const STATE_VERSION: u32 = 1;
+
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.to_le_bytes());
+}
+
+fn read_state_version() -> u32 {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("Invalid state version")))
+        .unwrap_or(0)
+}
+
+/// State layout of the first release, which read and wrote the whole order
+/// book on every call
+#[derive(BorshDeserialize)]
+struct LimitOrderProtocolV0 {
+    owner_id: AccountId,
+    orders: HashMap<String, OrderV0>,
+    /// Rebuilt from the orders, which name their maker
+    #[allow(dead_code)]
+    orders_by_maker: HashMap<AccountId, Vec<String>>,
+    next_order_id: u64,
+}
+
+/// Order layout of the V0 state. Fills reduced the amounts in place, so they
+/// are what remains of the order, and orders held no escrow.
+#[derive(BorshDeserialize)]
+struct OrderV0 {
+    id: String,
+    maker_asset: AccountId,
+    taker_asset: AccountId,
+    making_amount: u128,
+    taking_amount: u128,
+    maker: AccountId,
+    expiration: Timestamp,
+    status: OrderStatus,
+    created_at: Timestamp,
+}
+
+impl From<OrderV0> for Order {
+    /// Partially filled orders continue as orders for their remaining amounts,
+    /// to be funded by their maker
+    fn from(order: OrderV0) -> Self {
+        Self {
+            id: order.id,
+            maker_asset: order.maker_asset,
+            taker_asset: order.taker_asset,
+            making_amount: order.making_amount,
+            taking_amount: order.taking_amount,
+            filled_making_amount: 0,
+            maker: order.maker,
+            expiration: order.expiration,
+            status: order.status,
+            created_at: order.created_at,
+            escrowed_amount: 0,
+            storage_deposit: 0,
+            fills_in_flight: 0,
+            nonce: None,
+            series: 0,
+            epoch: 0,
+            auction: None,
+            allowed_taker: None,
+            solvers_only: false,
+            predicate: None,
+            pre_interaction: None,
+            post_interaction: None,
+            integrator_fee: None,
+            kind: OrderKind::Limit,
+            receiver: None,
+        }
+    }
+}
+
+#[near]
+impl LimitOrderProtocol {
+    /// Bring the state to the current layout. A current state is kept as it
+    /// is. The V0 state is converted to storage collections, keeping only open
+    /// orders, closed ones are in the events. The whole V0 state is rewritten
+    /// in this call, so it must fit in a single transaction.
+    #[init(ignore_state)]
+    #[payable]
+    #[private]
+    pub fn migrate() -> Self {
+        assert_one_yocto();
+        match read_state_version() {
+            STATE_VERSION => return env::state_read().expect("Failed to read contract state"),
+            0 => {}
+            _ => env::panic_str("Unknown state version"),
+        }
+        let old: LimitOrderProtocolV0 = env::state_read().expect("Failed to read contract state");
+
+        let mut this = Self::new(old.owner_id);
+        this.next_order_id = old.next_order_id;
+
+        for (order_id, order) in old.orders {
+            if order.status != OrderStatus::Open {
+                continue;
+            }
+            let order = Order::from(order);
+            let pair = order.pair();
+            this.internal_maker_orders_mut(&order.maker)
+                .insert(order_id.clone());
+            this.internal_pair_orders_mut(&pair)
+                .insert(order_id.clone());
+            this.internal_active_pair_orders_mut(&pair)
+                .insert(order_id.clone());
+            this.open_orders.insert(order_id.clone());
+            this.custodied_tokens.insert(order.maker_asset.clone());
+            this.custodied_tokens.insert(order.taker_asset.clone());
+            this.orders.insert(order_id, order);
+        }
+
+        this
+    }
+
+    pub fn upgrade(&mut self) -> PromiseOrValue<AccountId> {
+        self.assert_owner();
+        let code = env::input().expect("Code not found");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call_weight(
+                "migrate".into(),
+                vec![],
+                NearToken::from_yoctonear(1),
+                Gas::from_tgas(0),
+                GasWeight(1),
+            )
+            .function_call_weight(
+                "get_owner_id".into(),
+                vec![],
+                NearToken::from_millinear(0),
+                Gas::from_tgas(10),
+                GasWeight(0),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,191 @@
+use crate::math::{cmp_price, mul_div, Rounding};
+use crate::*;
+
+#[near]
+impl LimitOrderProtocol {
+    /// Page through the pairs that have open orders
+    pub fn get_pairs(&self, from_index: u32, limit: u32) -> Vec<Pair> {
+        self.active_orders_by_pair
+            .keys()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Page through the orders of a pair, optionally filtered by status, with
+    /// iceberg orders showing only their clip. Filtering on `Open` reads the
+    /// active index. Closed orders are only kept until their last fill is
+    /// settled, their history is in the events.
+    pub fn get_orders_by_pair(
+        &self,
+        pair: Pair,
+        from_index: u32,
+        limit: u32,
+        status_filter: Option<OrderStatus>,
+    ) -> Vec<OrderInfo> {
+        if status_filter == Some(OrderStatus::Open) {
+            self.active_orders_by_pair
+                .get(&pair)
+                .map(|order_ids| {
+                    self.internal_order_page(order_ids.iter(), from_index, limit, status_filter)
+                })
+                .unwrap_or_default()
+        } else {
+            self.orders_by_pair
+                .get(&pair)
+                .map(|order_ids| {
+                    self.internal_order_page(order_ids.iter(), from_index, limit, status_filter)
+                })
+                .unwrap_or_default()
+        }
+    }
+
+    /// Fillable orders in a page of a pair's open orders, cheapest for the
+    /// taker first. Orders are ranked by their current taking amount per
+    /// making amount, auction bump included, and orders at the same price by
+    /// creation time. Auction prices move with time, so there is no sorted
+    /// index: only the `limit` open orders from `from_index` are ranked, and
+    /// callers page through the whole pair to find its best price.
+    pub fn get_best_orders(&self, pair: Pair, from_index: u32, limit: u32) -> Vec<OrderInfo> {
+        let mut orders: Vec<(&Order, u128)> = self
+            .active_orders_by_pair
+            .get(&pair)
+            .map(|order_ids| {
+                order_ids
+                    .iter()
+                    .skip(from_index as usize)
+                    .take(limit as usize)
+                    .filter_map(|id| self.orders.get(id))
+                    .filter(|order| {
+                        order.status == OrderStatus::Open
+                            && !order.is_expired()
+                            && order.escrowed_amount > 0
+                            && order.fillable_making_amount() > 0
+                    })
+                    .map(|order| (order, order.current_taking_amount()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        orders.sort_by(|(a, a_taking), (b, b_taking)| {
+            cmp_price(*a_taking, a.making_amount, *b_taking, b.making_amount)
+                .then(a.created_at.cmp(&b.created_at))
+        });
+
+        orders
+            .into_iter()
+            .map(|(order, _)| book_order_info(order))
+            .collect()
+    }
+
+    /// What a fill of `amount` would do now: `amount` is the maker asset to
+    /// buy if `by_making`, otherwise the taker asset to spend. The fill is
+    /// checked as `fill_order` checks it, for `taker` if given and paid out as
+    /// native NEAR if `unwrap_near`. Predicates that read other contracts and
+    /// solver permissions cannot be checked by a view.
+    pub fn quote_fill(
+        &self,
+        order_id: String,
+        amount: U128,
+        by_making: bool,
+        taker: Option<AccountId>,
+        unwrap_near: Option<bool>,
+    ) -> FillQuote {
+        let order = self.orders.get(&order_id).expect("Order not found");
+
+        // Buying by making amount, the taker provides whatever the fill costs
+        let (provided_amount, making_amount) = if by_making {
+            (u128::MAX, Some(amount.0))
+        } else {
+            (amount.0, None)
+        };
+        let fillable = order.check_fillable(&order.taker_asset, taker.as_ref());
+        let unwrap = self.check_unwrap(order, unwrap_near.unwrap_or(false));
+        let checked_amounts = order.checked_fill_amounts(provided_amount, making_amount, None);
+        let predicate_holds = order.predicate.as_ref().map_or(Some(true), |predicate| {
+            let mut calls = vec![];
+            predicate.view_calls(&mut calls);
+            calls
+                .is_empty()
+                .then(|| self.evaluate_predicate(&order.maker, predicate, &mut std::iter::empty()))
+        });
+        let taker_permitted = (!order.solvers_only).then(|| {
+            taker.as_ref().is_none_or(|taker| {
+                order
+                    .allowed_taker
+                    .as_ref()
+                    .is_none_or(|allowed_taker| allowed_taker == taker)
+            })
+        });
+        let rejection = fillable
+            .err()
+            .or(unwrap.err())
+            .or((predicate_holds == Some(false)).then_some(FillRejection::PredicateNotSatisfied))
+            .or(checked_amounts.err());
+
+        let (maker_amount, taker_amount) = order.fill_amounts(amount.0, by_making);
+        let (protocol_fee, integrator_fee) = self.fill_fees(order, taker_amount);
+        // Iceberg orders only show the clip left after the fill
+        let mut filled_order = order.clone();
+        filled_order.filled_making_amount += maker_amount;
+        let filled_info = book_order_info(&filled_order);
+
+        FillQuote {
+            maker_amount: U128(maker_amount),
+            taker_amount: U128(taker_amount),
+            protocol_fee: U128(protocol_fee),
+            integrator_fee: U128(integrator_fee),
+            remaining_making_amount: U128(filled_info.remaining_making_amount),
+            remaining_taking_amount: U128(filled_info.remaining_taking_amount),
+            is_expired: order.is_expired(),
+            taker_permitted,
+            predicate_holds,
+            rejection,
+        }
+    }
+}
+
+impl LimitOrderProtocol {
+    fn internal_order_page<'a>(
+        &self,
+        order_ids: impl Iterator<Item = &'a String>,
+        from_index: u32,
+        limit: u32,
+        status_filter: Option<OrderStatus>,
+    ) -> Vec<OrderInfo> {
+        order_ids
+            .filter_map(|id| self.orders.get(id))
+            .filter(|order| {
+                status_filter
+                    .as_ref()
+                    .is_none_or(|status| &order.status == status)
+            })
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(book_order_info)
+            .collect()
+    }
+}
+
+/// Order as shown in the order book and order views: iceberg orders only
+/// show their visible clip, as if it were the whole order
+pub(crate) fn book_order_info(order: &Order) -> OrderInfo {
+    let mut info = OrderInfo::from(order);
+    if let OrderKind::Iceberg { .. } = order.kind {
+        let visible = order.fillable_making_amount();
+        let visible_taking = mul_div(
+            visible,
+            order.taking_amount,
+            order.making_amount,
+            Rounding::Up,
+        );
+        info.making_amount = visible;
+        info.taking_amount = visible_taking;
+        info.filled_making_amount = 0;
+        info.remaining_making_amount = visible;
+        info.remaining_taking_amount = visible_taking;
+        info.escrowed_amount = order.escrowed_amount.min(visible);
+    }
+    info
+}
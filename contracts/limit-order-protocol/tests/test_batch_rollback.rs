@@ -0,0 +1,218 @@
+use near_sdk::NearToken;
+use near_workspaces::{Account, Contract};
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+
+/// Interaction calling `account`, which has no contract
+fn failing_interaction(account: &Account) -> Value {
+    json!({
+        "contract_id": account.id(),
+        "method_name": "missing_method",
+        "gas": "10000000000000"
+    })
+}
+
+async fn get_order(
+    limit_order_protocol: &Contract,
+    order_id: &str,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Ok(limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": order_id }))
+        .await?
+        .json()?)
+}
+
+#[tokio::test]
+async fn test_failed_batch_reverts_every_fill() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &taker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, 2 * MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, 2 * TAKING_AMOUNT).await?;
+
+    // The second order's pre interaction fails
+    let result = create_order(
+        &limit_order_protocol,
+        &maker,
+        &usdc,
+        &usdt,
+        MAKING_AMOUNT,
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": usdc.id(),
+                    "taker_asset": usdt.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "pre_interaction": failing_interaction(&owner)
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let result = ft_transfer_call(
+        &usdt,
+        &taker,
+        limit_order_protocol.id(),
+        2 * TAKING_AMOUNT,
+        json!("deposit"),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let result = taker
+        .call(limit_order_protocol.id(), "fill_orders")
+        .args_json(json!({
+            "fills": [
+                { "order_id": "order_1", "amount": TAKING_AMOUNT.to_string() },
+                { "order_id": "order_2", "amount": TAKING_AMOUNT.to_string() }
+            ]
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!result.json::<bool>()?);
+
+    // Neither fill went through and the taker's balance is restored
+    let balance: String = limit_order_protocol
+        .view("get_balance")
+        .args_json(json!({ "account_id": taker.id(), "token_id": usdt.id() }))
+        .await?
+        .json()?;
+    assert_eq!(balance, (2 * TAKING_AMOUNT).to_string());
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, 0);
+
+    for order_id in ["order_1", "order_2"] {
+        let order = get_order(&limit_order_protocol, order_id).await?;
+        assert_eq!(order["status"], json!("Open"));
+        assert_eq!(order["remaining_making_amount"], json!(MAKING_AMOUNT));
+        assert_eq!(order["escrowed_amount"], json!(MAKING_AMOUNT));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_match_reverts_both_fills() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker_a = create_account(&sandbox, "maker-a", 10).await?;
+    let maker_b = create_account(&sandbox, "maker-b", 10).await?;
+    let matcher = create_account(&sandbox, "matcher", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[
+            maker_a.id(),
+            maker_b.id(),
+            matcher.id(),
+            limit_order_protocol.id(),
+        ],
+    )
+    .await?;
+    for maker in [&maker_a, &maker_b] {
+        let _ =
+            deposit_order_storage(&limit_order_protocol, maker, NearToken::from_near(1)).await?;
+    }
+    let _ = mint(&usdc, &maker_a, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &maker_b, TAKING_AMOUNT).await?;
+
+    // The orders cross exactly, and B's pre interaction fails
+    let result = create_order(
+        &limit_order_protocol,
+        &maker_a,
+        &usdc,
+        &usdt,
+        MAKING_AMOUNT,
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    let result = ft_transfer_call(
+        &usdt,
+        &maker_b,
+        limit_order_protocol.id(),
+        TAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker_b.id(),
+                    "maker_asset": usdt.id(),
+                    "taker_asset": usdc.id(),
+                    "making_amount": TAKING_AMOUNT.to_string(),
+                    "taking_amount": MAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "pre_interaction": failing_interaction(&owner)
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let result = matcher
+        .call(limit_order_protocol.id(), "match_orders")
+        .args_json(json!({ "order_id_a": "order_1", "order_id_b": "order_2" }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!result.json::<bool>()?);
+
+    // Both orders are left as they were and nothing was paid out
+    let order = get_order(&limit_order_protocol, "order_1").await?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(order["escrowed_amount"], json!(MAKING_AMOUNT));
+    let order = get_order(&limit_order_protocol, "order_2").await?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(order["escrowed_amount"], json!(TAKING_AMOUNT));
+
+    assert_eq!(ft_balance_of(&usdt, maker_a.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdc, maker_b.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdc, matcher.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdt, matcher.id()).await?, 0);
+
+    Ok(())
+}
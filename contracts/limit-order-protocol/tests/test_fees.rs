@@ -0,0 +1,146 @@
+use near_sdk::NearToken;
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const PROTOCOL_FEE_BPS: u32 = 30;
+const INTEGRATOR_FEE_BPS: u32 = 50;
+const PROTOCOL_FEE: u128 = TAKING_AMOUNT * PROTOCOL_FEE_BPS as u128 / 10_000;
+const INTEGRATOR_FEE: u128 = TAKING_AMOUNT * INTEGRATOR_FEE_BPS as u128 / 10_000;
+
+#[tokio::test]
+async fn test_fill_pays_fees() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let integrator = create_account(&sandbox, "integrator", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[
+            maker.id(),
+            taker.id(),
+            owner.id(),
+            integrator.id(),
+            limit_order_protocol.id(),
+        ],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &owner, NearToken::from_near(1)).await?;
+    let _ =
+        deposit_order_storage(&limit_order_protocol, &integrator, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, TAKING_AMOUNT).await?;
+
+    let result = owner
+        .call(limit_order_protocol.id(), "set_protocol_fee")
+        .args_json(json!({ "fee_bps": PROTOCOL_FEE_BPS, "recipient": owner.id() }))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": usdc.id(),
+                    "taker_asset": usdt.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "integrator_fee": {
+                        "recipient": integrator.id(),
+                        "fee_bps": INTEGRATOR_FEE_BPS
+                    }
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let result = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdt,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // Both fees come out of the taker amount, the maker gets the rest
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, MAKING_AMOUNT);
+    assert_eq!(
+        ft_balance_of(&usdt, maker.id()).await?,
+        TAKING_AMOUNT - PROTOCOL_FEE - INTEGRATOR_FEE
+    );
+    for (recipient, fee) in [(&owner, PROTOCOL_FEE), (&integrator, INTEGRATOR_FEE)] {
+        let fee_balance: String = limit_order_protocol
+            .view("get_fee_balance")
+            .args_json(json!({ "account_id": recipient.id(), "token_id": usdt.id() }))
+            .await?
+            .json()?;
+        assert_eq!(fee_balance, fee.to_string());
+    }
+
+    let event: Value = result
+        .logs()
+        .iter()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<Value>, _>>()?
+        .into_iter()
+        .find(|event| event.get("integrator_fee").is_some())
+        .expect("No OrderFilled event");
+    assert_eq!(event["order_id"], json!("order_1"));
+    assert_eq!(event["maker"], json!(maker.id()));
+    assert_eq!(event["taker"], json!(taker.id()));
+    assert_eq!(event["maker_asset"], json!(usdc.id()));
+    assert_eq!(event["taker_asset"], json!(usdt.id()));
+    assert_eq!(event["maker_amount"], json!(MAKING_AMOUNT));
+    assert_eq!(event["taker_amount"], json!(TAKING_AMOUNT));
+    assert_eq!(event["protocol_fee"], json!(PROTOCOL_FEE));
+    assert_eq!(event["integrator_fee"], json!(INTEGRATOR_FEE));
+    assert_eq!(event["integrator"], json!(integrator.id()));
+
+    // Each recipient withdraws their own fees
+    for (recipient, fee) in [(&owner, PROTOCOL_FEE), (&integrator, INTEGRATOR_FEE)] {
+        let result = recipient
+            .call(limit_order_protocol.id(), "withdraw_fees")
+            .args_json(json!({ "token_id": usdt.id() }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(result.failures().is_empty(), "{:#?}", result.failures());
+        assert_eq!(ft_balance_of(&usdt, recipient.id()).await?, fee);
+
+        let fee_balance: String = limit_order_protocol
+            .view("get_fee_balance")
+            .args_json(json!({ "account_id": recipient.id(), "token_id": usdt.id() }))
+            .await?
+            .json()?;
+        assert_eq!(fee_balance, "0");
+    }
+    assert_eq!(ft_balance_of(&usdt, limit_order_protocol.id()).await?, 0);
+
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use near_sdk::NearToken;
+use near_workspaces::Account;
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+
+/// Interaction calling `account`, which has no contract
+fn failing_interaction(account: &Account) -> Value {
+    json!({
+        "contract_id": account.id(),
+        "method_name": "missing_method",
+        "gas": "10000000000000"
+    })
+}
+
+#[tokio::test]
+async fn test_failed_payout_reverts_fill() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    // The taker is not registered with the maker asset, so the payout fails
+    register_accounts(&[&usdc], &[maker.id(), limit_order_protocol.id()]).await?;
+    register_accounts(
+        &[&usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, TAKING_AMOUNT).await?;
+
+    let result = create_order(
+        &limit_order_protocol,
+        &maker,
+        &usdc,
+        &usdt,
+        MAKING_AMOUNT,
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let _ = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdt,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+
+    // The taker is refunded and the order is left as it was
+    assert_eq!(ft_balance_of(&usdt, taker.id()).await?, TAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, 0);
+    assert_eq!(
+        ft_balance_of(&usdc, limit_order_protocol.id()).await?,
+        MAKING_AMOUNT
+    );
+
+    let order: Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_1" }))
+        .await?
+        .json()?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(order["remaining_making_amount"], json!(MAKING_AMOUNT));
+    assert_eq!(order["escrowed_amount"], json!(MAKING_AMOUNT));
+
+    // Once registered, the taker fills the order
+    let _ = storage_deposit(&usdc, taker.id()).await?;
+    let result = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdt,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, MAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, TAKING_AMOUNT);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_interaction_reverts_fill() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, TAKING_AMOUNT).await?;
+
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": usdc.id(),
+                    "taker_asset": usdt.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "pre_interaction": failing_interaction(&owner)
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let _ = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdt,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+
+    // Nothing left the protocol and the taker is refunded
+    assert_eq!(ft_balance_of(&usdt, taker.id()).await?, TAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, 0);
+
+    let order: Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_1" }))
+        .await?
+        .json()?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(order["remaining_making_amount"], json!(MAKING_AMOUNT));
+    assert_eq!(order["escrowed_amount"], json!(MAKING_AMOUNT));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_post_interaction_keeps_fill() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, TAKING_AMOUNT).await?;
+
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": usdc.id(),
+                    "taker_asset": usdt.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "post_interaction": failing_interaction(&owner)
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let result = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdt,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+
+    // The post interaction runs once both sides are paid, so its failure is
+    // only reported
+    let failure = format!("\"contract_id\":\"{}\"", owner.id());
+    assert!(result
+        .logs()
+        .iter()
+        .any(|log| log.starts_with("EVENT_JSON:") && log.contains(&failure)));
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, MAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdt, taker.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, TAKING_AMOUNT);
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+const ORDER_COUNT: usize = 50;
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+
+/// Gas of creating and filling an order must not grow with the number of
+/// orders already in the book
+#[tokio::test]
+async fn test_gas_does_not_grow_with_order_book() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let wnear = create_wnear(&sandbox).await?;
+    let usdc = create_usdc(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    // Register accounts for NEP-141 tokens
+    register_accounts(
+        &[&wnear, &usdc],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+
+    let result =
+        deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(5)).await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    let _ = mint(&wnear, &maker, MAKING_AMOUNT * ORDER_COUNT as u128).await?;
+    let _ = mint(&usdc, &taker, TAKING_AMOUNT * 2).await?;
+
+    // Create orders and record the gas burnt by each
+    let mut create_gas = vec![];
+    for _ in 0..ORDER_COUNT {
+        let result = create_order(
+            &limit_order_protocol,
+            &maker,
+            &wnear,
+            &usdc,
+            MAKING_AMOUNT,
+            TAKING_AMOUNT,
+        )
+        .await?;
+        assert!(result.failures().is_empty(), "{:#?}", result.failures());
+        create_gas.push(result.total_gas_burnt.as_gas());
+    }
+    println!(
+        "\n [LOG] Create order gas: first {} Tgas, last {} Tgas",
+        create_gas[0] as f64 / 1e12,
+        create_gas[ORDER_COUNT - 1] as f64 / 1e12
+    );
+
+    // Fill the oldest and the newest order
+    let mut fill_gas = vec![];
+    for order_id in ["order_1".to_string(), format!("order_{}", ORDER_COUNT)] {
+        let result = fill_order(
+            &limit_order_protocol,
+            &taker,
+            &usdc,
+            &order_id,
+            TAKING_AMOUNT,
+        )
+        .await?;
+        assert!(result.failures().is_empty(), "{:#?}", result.failures());
+        fill_gas.push(result.total_gas_burnt.as_gas());
+    }
+    println!(
+        "\n [LOG] Fill order gas: first {} Tgas, last {} Tgas",
+        fill_gas[0] as f64 / 1e12,
+        fill_gas[1] as f64 / 1e12
+    );
+
+    // Allow for id lengths and collection bookkeeping, not for history size
+    assert!(create_gas[ORDER_COUNT - 1] <= create_gas[1] * 11 / 10);
+    assert!(fill_gas[1] <= fill_gas[0] * 11 / 10);
+
+    let result = limit_order_protocol
+        .view("get_orders_by_pair")
+        .args_json(json!({
+            "pair": {
+                "maker_asset": wnear.id(),
+                "taker_asset": usdc.id()
+            },
+            "from_index": 0,
+            "limit": ORDER_COUNT,
+            "status_filter": "Open"
+        }))
+        .await?;
+    let open_orders: Vec<serde_json::Value> = serde_json::from_slice(&result.result)?;
+    assert_eq!(open_orders.len(), ORDER_COUNT - 2);
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use near_sdk::NearToken;
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+
+#[tokio::test]
+async fn test_interaction_cannot_move_held_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, 2 * MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, TAKING_AMOUNT).await?;
+
+    let result = create_order(
+        &limit_order_protocol,
+        &maker,
+        &usdc,
+        &usdt,
+        MAKING_AMOUNT,
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // The taker's interaction tries to move the escrowed maker asset to them
+    let drain = json!({
+        "contract_id": usdc.id(),
+        "method_name": "ft_transfer",
+        "args": json!({
+            "receiver_id": taker.id(),
+            "amount": MAKING_AMOUNT.to_string()
+        })
+        .to_string(),
+        "gas": "10000000000000"
+    });
+    let result = ft_transfer_call(
+        &usdt,
+        &taker,
+        limit_order_protocol.id(),
+        TAKING_AMOUNT,
+        json!({
+            "fill": {
+                "order_id": "order_1",
+                "interaction": drain
+            }
+        }),
+    )
+    .await?;
+    assert!(!result.failures().is_empty());
+
+    // Nothing left the protocol and the taker is refunded
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdt, taker.id()).await?, TAKING_AMOUNT);
+    assert_eq!(
+        ft_balance_of(&usdc, limit_order_protocol.id()).await?,
+        MAKING_AMOUNT
+    );
+
+    let order: Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_1" }))
+        .await?
+        .json()?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(order["escrowed_amount"], json!(MAKING_AMOUNT));
+
+    // Orders cannot carry such an interaction either
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": usdc.id(),
+                    "taker_asset": usdt.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "post_interaction": drain
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(!result.failures().is_empty());
+    assert_eq!(ft_balance_of(&usdc, maker.id()).await?, MAKING_AMOUNT);
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use near_gas::NearGas;
+use near_sdk::NearToken;
+use near_workspaces::{operations::Function, Account, Contract};
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const TAKING_AMOUNT: u128 = 5_000_000;
+
+fn making_amount() -> NearToken {
+    NearToken::from_near(5)
+}
+
+fn native_order(maker: &Account, wnear_id: &str, taker_asset: &Contract) -> Value {
+    json!({
+        "order": {
+            "maker": maker.id(),
+            "maker_asset": wnear_id,
+            "taker_asset": taker_asset.id(),
+            "making_amount": making_amount().as_yoctonear().to_string(),
+            "taking_amount": TAKING_AMOUNT.to_string(),
+            "expiration": NO_EXPIRATION,
+            "nonce": 0
+        },
+        "native": true
+    })
+}
+
+async fn set_wnear_id(
+    limit_order_protocol: &Contract,
+    owner: &Account,
+    wnear_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let result = owner
+        .call(limit_order_protocol.id(), "set_wnear_id")
+        .args_json(json!({ "wnear_id": wnear_id }))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    Ok(())
+}
+
+async fn get_order(
+    limit_order_protocol: &Contract,
+    order_id: &str,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Ok(limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": order_id }))
+        .await?
+        .json()?)
+}
+
+#[tokio::test]
+async fn test_native_order_filled_unwrapped() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let wnear = create_wnear(&sandbox).await?;
+    let usdc = create_usdc(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(&[&wnear], &[limit_order_protocol.id()]).await?;
+    register_accounts(
+        &[&usdc],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &taker, TAKING_AMOUNT).await?;
+    set_wnear_id(&limit_order_protocol, &owner, wnear.id().as_str()).await?;
+
+    // The attached NEAR is wrapped into the order's escrow
+    let result = maker
+        .call(limit_order_protocol.id(), "create_order")
+        .args_json(native_order(&maker, wnear.id().as_str(), &usdc))
+        .deposit(making_amount())
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert_eq!(result.json::<String>()?, "order_1");
+
+    let order = get_order(&limit_order_protocol, "order_1").await?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(
+        ft_balance_of(&wnear, limit_order_protocol.id()).await?,
+        making_amount().as_yoctonear()
+    );
+
+    // The taker is paid in NEAR, unwrapped from the escrow
+    let taker_balance = taker.view_account().await?.balance;
+    let result = ft_transfer_call(
+        &usdc,
+        &taker,
+        limit_order_protocol.id(),
+        TAKING_AMOUNT,
+        json!({
+            "fill": {
+                "order_id": "order_1",
+                "unwrap_near": true
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let received = taker
+        .view_account()
+        .await?
+        .balance
+        .saturating_sub(taker_balance);
+    assert!(received > making_amount().saturating_sub(NearToken::from_millinear(100)));
+    assert_eq!(ft_balance_of(&wnear, limit_order_protocol.id()).await?, 0);
+    assert_eq!(ft_balance_of(&wnear, taker.id()).await?, 0);
+    assert_eq!(ft_balance_of(&usdc, maker.id()).await?, TAKING_AMOUNT);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_wrap_cancels_order() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    // The configured wNEAR account has no contract, so wrapping fails
+    let broken_wnear = create_account(&sandbox, "broken-wnear", 1).await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    set_wnear_id(&limit_order_protocol, &owner, broken_wnear.id().as_str()).await?;
+
+    let maker_balance = maker.view_account().await?.balance;
+    let result = maker
+        .call(limit_order_protocol.id(), "create_order")
+        .args_json(native_order(&maker, broken_wnear.id().as_str(), &usdc))
+        .deposit(making_amount())
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    // The order is cancelled and the maker gets their NEAR back
+    let order = get_order(&limit_order_protocol, "order_1").await?;
+    assert!(order.is_null());
+    let spent = maker_balance.saturating_sub(maker.view_account().await?.balance);
+    assert!(spent < NearToken::from_millinear(100));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wrap_after_cancel_credits_balance() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let wnear = create_wnear(&sandbox).await?;
+    let usdc = create_usdc(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(&[&wnear], &[maker.id(), limit_order_protocol.id()]).await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    set_wnear_id(&limit_order_protocol, &owner, wnear.id().as_str()).await?;
+
+    // The order is cancelled in the same receipt, before its NEAR is wrapped
+    let result = maker
+        .batch(limit_order_protocol.id())
+        .call(
+            Function::new("create_order")
+                .args_json(native_order(&maker, wnear.id().as_str(), &usdc))
+                .deposit(making_amount())
+                .gas(NearGas::from_tgas(150)),
+        )
+        .call(
+            Function::new("cancel_order")
+                .args_json(json!({ "order_id": "order_1" }))
+                .gas(NearGas::from_tgas(50)),
+        )
+        .transact()
+        .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // The wrapped NEAR is kept in the maker's balance
+    let order = get_order(&limit_order_protocol, "order_1").await?;
+    assert!(order.is_null());
+    let balance: String = limit_order_protocol
+        .view("get_balance")
+        .args_json(json!({ "account_id": maker.id(), "token_id": wnear.id() }))
+        .await?
+        .json()?;
+    assert_eq!(balance, making_amount().as_yoctonear().to_string());
+    assert_eq!(
+        ft_balance_of(&wnear, limit_order_protocol.id()).await?,
+        making_amount().as_yoctonear()
+    );
+
+    Ok(())
+}
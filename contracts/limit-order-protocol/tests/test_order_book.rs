@@ -0,0 +1,77 @@
+use near_sdk::NearToken;
+use near_workspaces::Contract;
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+
+/// Ids of the best orders in a page of the pair's open orders
+async fn best_order_ids(
+    limit_order_protocol: &Contract,
+    pair: &Value,
+    from_index: u32,
+    limit: u32,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let orders: Vec<Value> = limit_order_protocol
+        .view("get_best_orders")
+        .args_json(json!({ "pair": pair, "from_index": from_index, "limit": limit }))
+        .await?
+        .json()?;
+
+    Ok(orders
+        .iter()
+        .map(|order| order["id"].as_str().unwrap().to_string())
+        .collect())
+}
+
+#[tokio::test]
+async fn test_best_orders_ranked_within_page() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(&[&usdc, &usdt], &[maker.id(), limit_order_protocol.id()]).await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, 3 * MAKING_AMOUNT).await?;
+
+    // Orders 1 to 3, the cheapest one last
+    for taking_amount in [3_000_000, 2_000_000, 1_000_000u128] {
+        let result = create_order(
+            &limit_order_protocol,
+            &maker,
+            &usdc,
+            &usdt,
+            MAKING_AMOUNT,
+            taking_amount,
+        )
+        .await?;
+        assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    }
+
+    let pair = json!({ "maker_asset": usdc.id(), "taker_asset": usdt.id() });
+
+    // The whole book at once is ranked by price
+    assert_eq!(
+        best_order_ids(&limit_order_protocol, &pair, 0, 10).await?,
+        vec!["order_3", "order_2", "order_1"]
+    );
+    // Pages are ranked on their own
+    assert_eq!(
+        best_order_ids(&limit_order_protocol, &pair, 0, 2).await?,
+        vec!["order_2", "order_1"]
+    );
+    assert_eq!(
+        best_order_ids(&limit_order_protocol, &pair, 2, 2).await?,
+        vec!["order_3"]
+    );
+
+    Ok(())
+}
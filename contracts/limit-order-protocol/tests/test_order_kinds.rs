@@ -0,0 +1,115 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const CLIP: u128 = 100_000;
+
+#[tokio::test]
+async fn test_iceberg_order_fills_by_clip() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let wnear = create_wnear(&sandbox).await?;
+    let usdc = create_usdc(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&wnear, &usdc],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&wnear, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdc, &taker, TAKING_AMOUNT).await?;
+
+    let result = ft_transfer_call(
+        &wnear,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": wnear.id(),
+                    "taker_asset": usdc.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "kind": { "Iceberg": { "clip": CLIP.to_string() } }
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // The book only shows the clip
+    let pair = json!({
+        "maker_asset": wnear.id(),
+        "taker_asset": usdc.id()
+    });
+    let orders: Vec<serde_json::Value> = limit_order_protocol
+        .view("get_best_orders")
+        .args_json(json!({ "pair": pair, "from_index": 0, "limit": 10 }))
+        .await?
+        .json()?;
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0]["remaining_making_amount"], json!(CLIP));
+
+    // A quote shows the next clip as what remains, not the hidden amount
+    let quote: serde_json::Value = limit_order_protocol
+        .view("quote_fill")
+        .args_json(json!({
+            "order_id": "order_1",
+            "amount": CLIP.to_string(),
+            "by_making": true
+        }))
+        .await?
+        .json()?;
+    assert_eq!(quote["maker_amount"], json!(CLIP.to_string()));
+    assert_eq!(quote["remaining_making_amount"], json!(CLIP.to_string()));
+    assert_eq!(
+        quote["remaining_taking_amount"],
+        json!((CLIP * TAKING_AMOUNT / MAKING_AMOUNT).to_string())
+    );
+
+    // A fill larger than the clip only takes the clip, the rest is refunded
+    let result = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdc,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let balance: String = wnear
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": taker.id() }))
+        .await?
+        .json()?;
+    assert_eq!(balance, CLIP.to_string());
+
+    let order: serde_json::Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_1" }))
+        .await?
+        .json()?;
+    // The order view shows the next clip only, not what was filled
+    assert_eq!(order["remaining_making_amount"], json!(CLIP));
+    assert_eq!(order["filled_making_amount"], json!(0));
+    assert_eq!(order["status"], json!("Open"));
+
+    Ok(())
+}
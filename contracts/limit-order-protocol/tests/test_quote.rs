@@ -0,0 +1,152 @@
+use near_sdk::NearToken;
+use near_workspaces::{Account, Contract};
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const FILL_AMOUNT: u128 = 500_001;
+const PROTOCOL_FEE_BPS: u32 = 30;
+
+async fn quote_fill(
+    limit_order_protocol: &Contract,
+    order_id: &str,
+    taker: &Account,
+    unwrap_near: bool,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Ok(limit_order_protocol
+        .view("quote_fill")
+        .args_json(json!({
+            "order_id": order_id,
+            "amount": FILL_AMOUNT.to_string(),
+            "by_making": false,
+            "taker": taker.id(),
+            "unwrap_near": unwrap_near
+        }))
+        .await?
+        .json()?)
+}
+
+fn amount(value: &Value) -> u128 {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_quote_matches_fill() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, 2 * MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, FILL_AMOUNT).await?;
+
+    let result = owner
+        .call(limit_order_protocol.id(), "set_protocol_fee")
+        .args_json(json!({ "fee_bps": PROTOCOL_FEE_BPS, "recipient": owner.id() }))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    let result = create_order(
+        &limit_order_protocol,
+        &maker,
+        &usdc,
+        &usdt,
+        MAKING_AMOUNT,
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // The maker asset is not wNEAR, so it cannot be unwrapped
+    let quote = quote_fill(&limit_order_protocol, "order_1", &taker, true).await?;
+    assert_eq!(quote["rejection"], json!("CannotUnwrap"));
+
+    let quote = quote_fill(&limit_order_protocol, "order_1", &taker, false).await?;
+    assert_eq!(quote["rejection"], Value::Null);
+    assert_eq!(quote["taker_permitted"], json!(true));
+    assert_eq!(quote["predicate_holds"], json!(true));
+
+    let result = fill_order(&limit_order_protocol, &taker, &usdt, "order_1", FILL_AMOUNT).await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // The fill went through exactly as quoted
+    let taker_amount = amount(&quote["taker_amount"]);
+    let protocol_fee = amount(&quote["protocol_fee"]);
+    assert!(protocol_fee > 0);
+    assert_eq!(
+        ft_balance_of(&usdc, taker.id()).await?,
+        amount(&quote["maker_amount"])
+    );
+    assert_eq!(
+        ft_balance_of(&usdt, taker.id()).await?,
+        FILL_AMOUNT - taker_amount
+    );
+    assert_eq!(
+        ft_balance_of(&usdt, maker.id()).await?,
+        taker_amount - protocol_fee
+    );
+    let fee_balance: String = limit_order_protocol
+        .view("get_fee_balance")
+        .args_json(json!({ "account_id": owner.id(), "token_id": usdt.id() }))
+        .await?
+        .json()?;
+    assert_eq!(fee_balance, protocol_fee.to_string());
+
+    let order: Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_1" }))
+        .await?
+        .json()?;
+    assert_eq!(
+        order["remaining_making_amount"].as_u64().unwrap() as u128,
+        amount(&quote["remaining_making_amount"])
+    );
+    assert_eq!(
+        order["remaining_taking_amount"].as_u64().unwrap() as u128,
+        amount(&quote["remaining_taking_amount"])
+    );
+
+    // Solver permissions are not known to a view
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": usdc.id(),
+                    "taker_asset": usdt.id(),
+                    "making_amount": MAKING_AMOUNT.to_string(),
+                    "taking_amount": TAKING_AMOUNT.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0,
+                    "solvers_only": true
+                }
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    let quote = quote_fill(&limit_order_protocol, "order_2", &taker, false).await?;
+    assert_eq!(quote["taker_permitted"], Value::Null);
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use near_sdk::NearToken;
+use serde_json::{json, Value};
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+const NONCE: u64 = 1;
+
+/// Key the maker signs with. Its secret key is
+/// ed25519:33ddPCA4AW7RbMQMLTkgmKsm5wyzVKTwEx2WnaQsZ8gWtm564fr4fuF86w4mhTp2fu5cLLuPq2eGwpwD772QEajX
+const SIGNING_PUBLIC_KEY: &str = "ed25519:DUrNyrCD75XiXaZMKVeuy9So2VH547znnctbXXEP8FbT";
+
+/// sha256 of the borsh serialized order built by `signed_order`
+const ORDER_HASH: &str = "8e08de0e589e478d552cfecb7f761648064549763877b870c2b0c0191eb00a3c";
+
+/// Signature of the order as a wallet's NEP-413 `signMessage` produces it,
+/// for the message `ORDER_HASH`, the order hash as nonce and
+/// `limit-order.test.near` as recipient. It was computed off-chain and not
+/// with the contract's own code.
+const ORDER_SIGNATURE: &str =
+    "gFslNrhZ93eHnmGSuiRWbv9yRYtDZYxChzbG0dwNU58XJoh0OTUTmlSLbtbJaWHRHf2TmiEpWjFbkrm7B6YbCQ==";
+
+fn signed_order(signature: &str) -> Value {
+    json!({
+        "order": {
+            "maker": "maker.test.near",
+            "maker_asset": "usdc.test.near",
+            "taker_asset": "usdt.test.near",
+            "making_amount": MAKING_AMOUNT.to_string(),
+            "taking_amount": TAKING_AMOUNT.to_string(),
+            "expiration": NO_EXPIRATION,
+            "nonce": NONCE
+        },
+        "public_key": SIGNING_PUBLIC_KEY,
+        "signature": signature
+    })
+}
+
+#[tokio::test]
+async fn test_fill_wallet_signed_order() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = deposit_order_storage(&limit_order_protocol, &maker, NearToken::from_near(1)).await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, 2 * TAKING_AMOUNT).await?;
+
+    // The maker registers the key and deposits the making amount, no order is created
+    let result = maker
+        .call(limit_order_protocol.id(), "add_signing_key")
+        .args_json(json!({ "public_key": SIGNING_PUBLIC_KEY }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!("deposit"),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // A signature that does not match the order is rejected and the taker refunded
+    let mut tampered = ORDER_SIGNATURE.to_string();
+    tampered.replace_range(0..1, "A");
+    let result = ft_transfer_call(
+        &usdt,
+        &taker,
+        limit_order_protocol.id(),
+        TAKING_AMOUNT,
+        json!({
+            "fill": {
+                "order_id": ORDER_HASH,
+                "signed_order": signed_order(&tampered)
+            }
+        }),
+    )
+    .await?;
+    assert!(!result.failures().is_empty());
+    assert_eq!(ft_balance_of(&usdt, taker.id()).await?, 2 * TAKING_AMOUNT);
+    let order: Option<Value> = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": ORDER_HASH }))
+        .await?
+        .json()?;
+    assert!(order.is_none());
+
+    // The wallet signature creates the order on its first fill
+    let result = ft_transfer_call(
+        &usdt,
+        &taker,
+        limit_order_protocol.id(),
+        TAKING_AMOUNT,
+        json!({
+            "fill": {
+                "order_id": ORDER_HASH,
+                "signed_order": signed_order(ORDER_SIGNATURE)
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, MAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdt, maker.id()).await?, TAKING_AMOUNT);
+
+    // The signed order cannot be replayed once filled
+    let result = ft_transfer_call(
+        &usdt,
+        &taker,
+        limit_order_protocol.id(),
+        TAKING_AMOUNT,
+        json!({
+            "fill": {
+                "order_id": ORDER_HASH,
+                "signed_order": signed_order(ORDER_SIGNATURE)
+            }
+        }),
+    )
+    .await?;
+    assert!(!result.failures().is_empty());
+    assert_eq!(ft_balance_of(&usdc, taker.id()).await?, MAKING_AMOUNT);
+    assert_eq!(ft_balance_of(&usdt, taker.id()).await?, TAKING_AMOUNT);
+
+    Ok(())
+}
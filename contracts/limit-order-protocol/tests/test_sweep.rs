@@ -0,0 +1,115 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+
+#[tokio::test]
+async fn test_sweep_expired_orders() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let wnear = create_wnear(&sandbox).await?;
+    let usdc = create_usdc(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let sweeper = create_account(&sandbox, "sweeper", 10).await?;
+    let limit_order_protocol = deploy_limit_order_protocol(&sandbox, &owner).await?;
+
+    register_accounts(&[&wnear, &usdc], &[maker.id(), limit_order_protocol.id()]).await?;
+    let _ = mint(&wnear, &maker, MAKING_AMOUNT).await?;
+
+    // Create an order expiring shortly, paying for its storage, and fund it
+    let expiration = sandbox.view_block().await?.timestamp() + 10_000_000_000;
+    let result = maker
+        .call(limit_order_protocol.id(), "create_order")
+        .args_json(json!({
+            "order": {
+                "maker": maker.id(),
+                "maker_asset": wnear.id(),
+                "taker_asset": usdc.id(),
+                "making_amount": MAKING_AMOUNT.to_string(),
+                "taking_amount": TAKING_AMOUNT.to_string(),
+                "expiration": expiration,
+                "nonce": 0
+            }
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+    let order_id: String = result.json()?;
+
+    let order: serde_json::Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": order_id }))
+        .await?
+        .json()?;
+    assert!(order["storage_deposit"]
+        .as_f64()
+        .is_some_and(|deposit| deposit > 0.0));
+
+    let result = ft_transfer_call(
+        &wnear,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({
+            "fund_order": {
+                "order_id": order_id
+            }
+        }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    // Nothing to sweep before the expiration
+    let result = sweeper
+        .call(limit_order_protocol.id(), "sweep_expired")
+        .args_json(json!({ "limit": 10 }))
+        .transact()
+        .await?;
+    assert_eq!(result.json::<u32>()?, 0);
+
+    sandbox.fast_forward(100).await?;
+
+    let result = sweeper
+        .call(limit_order_protocol.id(), "sweep_expired")
+        .args_json(json!({ "limit": 10 }))
+        .transact()
+        .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+    assert_eq!(result.json::<u32>()?, 1);
+
+    // The order is gone and its escrow is back with the maker
+    let order: Option<serde_json::Value> = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": order_id }))
+        .await?
+        .json()?;
+    assert!(order.is_none());
+
+    let balance: String = wnear
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": maker.id() }))
+        .await?
+        .json()?;
+    assert_eq!(balance, MAKING_AMOUNT.to_string());
+
+    // The storage deposit is unlocked, less the sweeper's bounty
+    let storage: serde_json::Value = limit_order_protocol
+        .view("storage_balance_of")
+        .args_json(json!({ "account_id": maker.id() }))
+        .await?
+        .json()?;
+    let total: u128 = storage["total"].as_str().unwrap().parse()?;
+    let available: u128 = storage["available"].as_str().unwrap().parse()?;
+    assert!(total < NearToken::from_millinear(100).as_yoctonear());
+    assert!(available > 0);
+
+    Ok(())
+}
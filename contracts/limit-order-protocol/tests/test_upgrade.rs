@@ -0,0 +1,149 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+mod utils;
+
+use utils::*;
+
+/// First release of the contract, built by `make limit-order-protocol-v0`
+const LIMIT_ORDER_PROTOCOL_V0_CONTRACT_WASM: &str =
+    "../../target/near/limit_order_protocol_v0/limit_order_protocol.wasm";
+
+const MAKING_AMOUNT: u128 = 1_000_000;
+const TAKING_AMOUNT: u128 = 2_000_000;
+
+#[tokio::test]
+async fn test_migrate_from_v0() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let usdc = create_usdc(&sandbox).await?;
+    let usdt = create_usdt(&sandbox).await?;
+
+    let owner = create_account(&sandbox, "owner", 10).await?;
+    let maker = create_account(&sandbox, "maker", 10).await?;
+    let taker = create_account(&sandbox, "taker", 10).await?;
+
+    // Deploy the first release and create an open and a cancelled order
+    let v0_wasm =
+        std::fs::read(LIMIT_ORDER_PROTOCOL_V0_CONTRACT_WASM).expect("Contract wasm not found");
+    let account = create_account(&sandbox, "limit-order", 100).await?;
+    let limit_order_protocol = account.deploy(&v0_wasm).await?.result;
+    let result = limit_order_protocol
+        .call("new")
+        .args_json(json!({ "owner_id": owner.id() }))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    for _ in 0..2 {
+        let result = maker
+            .call(limit_order_protocol.id(), "create_order")
+            .args_json(json!({
+                "maker_asset": usdc.id(),
+                "taker_asset": usdt.id(),
+                "making_amount": MAKING_AMOUNT,
+                "taking_amount": TAKING_AMOUNT,
+                "maker": maker.id(),
+                "expiration": NO_EXPIRATION
+            }))
+            .transact()
+            .await?;
+        assert!(result.is_success(), "{:#?}", result.failures());
+    }
+    let result = maker
+        .call(limit_order_protocol.id(), "cancel_order")
+        .args_json(json!({ "order_id": "order_2" }))
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    // Deploy the current code over it and migrate the state
+    let wasm = std::fs::read(LIMIT_ORDER_PROTOCOL_CONTRACT_WASM).expect("Contract wasm not found");
+    let limit_order_protocol = account.deploy(&wasm).await?.result;
+    let result = account
+        .call(limit_order_protocol.id(), "migrate")
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+
+    let owner_id: String = limit_order_protocol.view("get_owner_id").await?.json()?;
+    assert_eq!(owner_id, owner.id().to_string());
+
+    // The open order is kept with its terms, the cancelled one is dropped
+    let order: serde_json::Value = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_1" }))
+        .await?
+        .json()?;
+    assert_eq!(order["status"], json!("Open"));
+    assert_eq!(order["maker"], json!(maker.id()));
+    assert_eq!(order["escrowed_amount"], json!(0));
+    let order: Option<serde_json::Value> = limit_order_protocol
+        .view("get_order")
+        .args_json(json!({ "order_id": "order_2" }))
+        .await?
+        .json()?;
+    assert!(order.is_none());
+
+    let orders: Vec<serde_json::Value> = limit_order_protocol
+        .view("get_orders_by_maker")
+        .args_json(json!({ "maker": maker.id() }))
+        .await?
+        .json()?;
+    assert_eq!(orders.len(), 1);
+
+    // Migrating the current state again leaves it as it is
+    let result = account
+        .call(limit_order_protocol.id(), "migrate")
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:#?}", result.failures());
+    let orders: Vec<serde_json::Value> = limit_order_protocol
+        .view("get_orders_by_maker")
+        .args_json(json!({ "maker": maker.id() }))
+        .await?
+        .json()?;
+    assert_eq!(orders.len(), 1);
+
+    // The maker funds the migrated order and it fills as any other
+    register_accounts(
+        &[&usdc, &usdt],
+        &[maker.id(), taker.id(), limit_order_protocol.id()],
+    )
+    .await?;
+    let _ = mint(&usdc, &maker, MAKING_AMOUNT).await?;
+    let _ = mint(&usdt, &taker, TAKING_AMOUNT).await?;
+
+    let result = ft_transfer_call(
+        &usdc,
+        &maker,
+        limit_order_protocol.id(),
+        MAKING_AMOUNT,
+        json!({ "fund_order": { "order_id": "order_1" } }),
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let result = fill_order(
+        &limit_order_protocol,
+        &taker,
+        &usdt,
+        "order_1",
+        TAKING_AMOUNT,
+    )
+    .await?;
+    assert!(result.failures().is_empty(), "{:#?}", result.failures());
+
+    let balance: String = usdc
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": taker.id() }))
+        .await?
+        .json()?;
+    assert_eq!(balance, MAKING_AMOUNT.to_string());
+
+    Ok(())
+}
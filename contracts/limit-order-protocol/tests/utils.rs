@@ -0,0 +1,282 @@
+#![allow(dead_code)]
+
+use near_contract_standards::fungible_token::Balance;
+use near_gas::NearGas;
+use near_sdk::{AccountId, NearToken};
+use near_workspaces::{network::Sandbox, result::ExecutionFinalResult, Account, Contract, Worker};
+use serde_json::{json, Value};
+
+pub const LIMIT_ORDER_PROTOCOL_CONTRACT_WASM: &str =
+    "../../target/near/limit_order_protocol/limit_order_protocol.wasm";
+pub const MOCK_FT_CONTRACT_WASM: &str = "../../target/near/mock_ft/mock_ft.wasm";
+
+/// Far enough in the future for orders created in tests to never expire
+pub const NO_EXPIRATION: u64 = 4_102_444_800_000_000_000;
+
+pub async fn create_account(
+    sandbox: &Worker<Sandbox>,
+    prefix: &str,
+    balance: Balance,
+) -> Result<Account, Box<dyn std::error::Error>> {
+    let root = sandbox.root_account().unwrap();
+    Ok(root
+        .create_subaccount(prefix)
+        .initial_balance(NearToken::from_near(balance))
+        .transact()
+        .await?
+        .result)
+}
+
+pub async fn create_ft(
+    sandbox: &Worker<Sandbox>,
+    name: &str,
+    symbol: &str,
+    decimals: u32,
+    total_supply: Balance,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    let mock_ft_contract_wasm =
+        std::fs::read(MOCK_FT_CONTRACT_WASM).expect("Contract wasm not found");
+
+    let ft_account = create_account(sandbox, symbol.to_lowercase().as_str(), 100).await?;
+    let ft_contract = ft_account.deploy(&mock_ft_contract_wasm).await?.result;
+    let result = ft_contract
+        .call("new")
+        .args_json(json!({
+            "owner_id": ft_contract.id(),
+            "total_supply": total_supply.to_string(),
+            "metadata": {
+                "spec": "ft-1.0.0".to_string(),
+                "name": name.to_string(),
+                "symbol": symbol.to_string(),
+                "icon": None::<String>,
+                "reference": None::<String>,
+                "reference_hash": None::<String>,
+                "decimals": decimals,
+            }
+        }))
+        .transact()
+        .await?;
+    assert!(
+        result.is_success(),
+        "{:#?}",
+        result.into_result().unwrap_err()
+    );
+
+    Ok(ft_contract)
+}
+
+pub async fn storage_deposit(
+    ft: &Contract,
+    account_id: &AccountId,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = ft
+        .call("storage_deposit")
+        .args_json(json!({
+            "account_id": account_id,
+            "registration_only": true
+        }))
+        .deposit(NearToken::from_millinear(1250))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn ft_transfer(
+    ft: &Contract,
+    sender: &Account,
+    receiver: &Account,
+    amount: Balance,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = sender
+        .call(ft.id(), "ft_transfer")
+        .args_json(json!({
+            "receiver_id": receiver.id(),
+            "amount": amount.to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn ft_transfer_call(
+    ft: &Contract,
+    sender: &Account,
+    receiver_id: &AccountId,
+    amount: Balance,
+    msg: Value,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = sender
+        .call(ft.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": receiver_id,
+            "amount": amount.to_string(),
+            "msg": msg.to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(NearGas::from_tgas(300))
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn ft_balance_of(
+    ft: &Contract,
+    account_id: &AccountId,
+) -> Result<Balance, Box<dyn std::error::Error>> {
+    let balance: String = ft
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": account_id }))
+        .await?
+        .json()?;
+
+    Ok(balance.parse()?)
+}
+
+pub async fn create_wnear(
+    sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    create_ft(
+        sandbox,
+        "Wrapped NEAR",
+        "wNEAR",
+        24,
+        NearToken::from_near(1_000_000_000).as_yoctonear(), // 1B
+    )
+    .await
+}
+
+pub async fn create_usdc(
+    sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    create_ft(sandbox, "USD Coin", "USDC", 6, 10_000_000_000_000_000).await
+}
+
+pub async fn create_usdt(
+    sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    create_ft(sandbox, "Tether USD", "USDT", 6, 10_000_000_000_000_000).await
+}
+
+/// Register every account with every token
+pub async fn register_accounts(
+    fts: &[&Contract],
+    account_ids: &[&AccountId],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for ft in fts {
+        for account_id in account_ids {
+            let result = storage_deposit(ft, account_id).await?;
+            assert!(result.is_success(), "{:#?}", result.failures());
+        }
+    }
+
+    Ok(())
+}
+
+/// Send `amount` of `ft` to `receiver` from the token's own supply
+pub async fn mint(
+    ft: &Contract,
+    receiver: &Account,
+    amount: Balance,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    ft_transfer(ft, ft.as_account(), receiver, amount).await
+}
+
+pub async fn deploy_limit_order_protocol(
+    sandbox: &Worker<Sandbox>,
+    owner: &Account,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    let limit_order_protocol_contract_wasm =
+        std::fs::read(LIMIT_ORDER_PROTOCOL_CONTRACT_WASM).expect("Contract wasm not found");
+    let limit_order_protocol_account = create_account(sandbox, "limit-order", 100).await?;
+    let limit_order_protocol_contract = limit_order_protocol_account
+        .deploy(&limit_order_protocol_contract_wasm)
+        .await?
+        .result;
+
+    let result = limit_order_protocol_contract
+        .call("new")
+        .args_json(json!({
+            "owner_id": owner.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(
+        result.is_success(),
+        "{:#?}",
+        result.into_result().unwrap_err()
+    );
+
+    Ok(limit_order_protocol_contract)
+}
+
+/// Add `amount` to the storage balance `account` pays its orders with
+pub async fn deposit_order_storage(
+    limit_order_protocol: &Contract,
+    account: &Account,
+    amount: NearToken,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    let result = account
+        .call(limit_order_protocol.id(), "storage_deposit")
+        .args_json(json!({}))
+        .deposit(amount)
+        .transact()
+        .await?;
+
+    Ok(result)
+}
+
+/// Create a fully funded order selling `making_amount` of `maker_asset`
+pub async fn create_order(
+    limit_order_protocol: &Contract,
+    maker: &Account,
+    maker_asset: &Contract,
+    taker_asset: &Contract,
+    making_amount: Balance,
+    taking_amount: Balance,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    ft_transfer_call(
+        maker_asset,
+        maker,
+        limit_order_protocol.id(),
+        making_amount,
+        json!({
+            "create_order": {
+                "order": {
+                    "maker": maker.id(),
+                    "maker_asset": maker_asset.id(),
+                    "taker_asset": taker_asset.id(),
+                    "making_amount": making_amount.to_string(),
+                    "taking_amount": taking_amount.to_string(),
+                    "expiration": NO_EXPIRATION,
+                    "nonce": 0
+                }
+            }
+        }),
+    )
+    .await
+}
+
+pub async fn fill_order(
+    limit_order_protocol: &Contract,
+    taker: &Account,
+    taker_asset: &Contract,
+    order_id: &str,
+    amount: Balance,
+) -> Result<ExecutionFinalResult, Box<dyn std::error::Error>> {
+    ft_transfer_call(
+        taker_asset,
+        taker,
+        limit_order_protocol.id(),
+        amount,
+        json!({
+            "fill": {
+                "order_id": order_id
+            }
+        }),
+    )
+    .await
+}
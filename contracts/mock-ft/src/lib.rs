@@ -10,7 +10,10 @@ use near_contract_standards::storage_management::{
 use near_sdk::borsh::BorshSerialize;
 use near_sdk::collections::LazyOption;
 use near_sdk::json_types::U128;
-use near_sdk::{log, near, AccountId, BorshStorageKey, NearToken, PanicOnDefault, PromiseOrValue};
+use near_sdk::{
+    assert_one_yocto, env, log, near, AccountId, BorshStorageKey, NearToken, PanicOnDefault,
+    Promise, PromiseOrValue,
+};
 
 #[derive(PanicOnDefault)]
 #[near(contract_state)]
@@ -70,6 +73,27 @@ impl Contract {
 
         this
     }
+
+    /// Mint tokens for the attached NEAR to the caller, as wNEAR does
+    #[payable]
+    pub fn near_deposit(&mut self) {
+        let account_id = env::predecessor_account_id();
+        if !self.token.accounts.contains_key(&account_id) {
+            self.token.internal_register_account(&account_id);
+        }
+        self.token
+            .internal_deposit(&account_id, env::attached_deposit().as_yoctonear());
+    }
+
+    /// Burn `amount` of the caller's tokens and send them as much NEAR, as
+    /// wNEAR does
+    #[payable]
+    pub fn near_withdraw(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.token.internal_withdraw(&account_id, amount.0);
+        Promise::new(account_id).transfer(NearToken::from_yoctonear(amount.0))
+    }
 }
 
 #[near]